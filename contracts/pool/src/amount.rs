@@ -0,0 +1 @@
+pub use pool_types::amount::*;
@@ -6,5 +6,6 @@ fn main() {
     write_api! {
         instantiate: InitMsg,
         execute: ExecuteMsg,
+        query: QueryMsg,
     }
 }
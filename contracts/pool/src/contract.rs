@@ -1,19 +1,49 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{ to_binary,  Binary, Deps, DepsMut, Env,  MessageInfo, Response,  StdResult};
-use cw2::set_contract_version;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Reply, Response, StdResult, SubMsg,
+    Uint128, WasmMsg,
+};
+use cw20::MinterResponse;
+use cw2::{get_contract_version, set_contract_version};
+use semver::Version;
 
+use crate::amount::validate_denom;
 use crate::error::ContractError;
-use crate::execute::{execute_withdraw_funds,};
-use crate::msg::{  ExecuteMsg,  MigrateMsg,  QueryMsg, InitMsg, };
+use crate::execute::{
+    execute_add_allowed_denom, execute_add_contribution_hook, execute_add_hook, execute_cancel,
+    execute_cancel_vesting, execute_claim_vested, execute_close, execute_contribute,
+    execute_pause, execute_receive, execute_refund, execute_release_milestone,
+    execute_remove_allowed_denom, execute_remove_contribution_hook, execute_remove_hook,
+    execute_unpause, execute_update_beneficiaries, execute_vote_milestone, execute_withdraw_funds,
+    validate_beneficiaries, validate_milestones,
+};
+use crate::migrations::{v1, LEGACY_CONTRACT_NAME};
+use crate::msg::{  ExecuteMsg,  MigrateMsg,  QueryMsg, InitMsg, Cw20InstantiateMsg};
 
-use crate::query::query_config;
-use crate::state::{Config, CONFIG};
+use crate::query::{
+    query_admin, query_beneficiaries, query_config, query_contribution, query_list_allowed_denoms,
+    query_list_contributions, query_list_members, query_member, query_milestones, query_status,
+    query_total_weight, query_vesting_status, query_withdrawn,
+};
+use crate::reply::{handle_contribution_hook_reply, handle_share_token_reply};
+use crate::state::{
+    Config, PoolStatus, ALLOW_LIST, BENEFICIARIES, CONFIG, CONTRIBUTION_HOOKS, FACTORY, HOOKS,
+    MILESTONES,
+    MILESTONE_THRESHOLD, RAISED, STATUS, TOKENS_PER_WEIGHT, TOTAL,
+};
 
 // version info for migration info
-const CONTRACT_NAME: &str = "crates.io:cw20-ics20";
+const CONTRACT_NAME: &str = "crates.io:pool";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub const SHARE_TOKEN_REPLY_ID: u64 = 0;
+/// contribution hooks are fire and forget, see `handle_contribution_hook_reply`
+pub const CONTRIBUTION_HOOK_REPLY_ID: u64 = 1;
+/// gas every contribution hook gets, so running out is an error we can ignore rather than
+/// aborting the whole transaction
+pub const CONTRIBUTION_HOOK_GAS_LIMIT: u64 = 200_000;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     mut _deps: DepsMut,
@@ -23,12 +53,86 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(_deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     
+    if msg.target.is_zero() {
+        return Err(ContractError::ZeroTarget {});
+    }
+    if msg.deadline.is_expired(&_env.block) {
+        return Err(ContractError::InvalidDeadline {});
+    }
+    validate_denom(_deps.api, &msg.denom)?;
+    ALLOW_LIST.save(_deps.storage, &msg.denom, &Empty {})?;
+    for denom in msg.allowed_denoms.iter() {
+        validate_denom(_deps.api, denom)?;
+        ALLOW_LIST.save(_deps.storage, denom, &Empty {})?;
+    }
+
+    let beneficiaries = validate_beneficiaries(_deps.api, msg.beneficiaries)?;
+    BENEFICIARIES.save(_deps.storage, &beneficiaries)?;
+    let milestones =
+        validate_milestones(&msg.deadline, msg.target, msg.milestones, msg.threshold.clone())?;
+    if let (false, Some(threshold)) = (milestones.is_empty(), msg.threshold) {
+        MILESTONE_THRESHOLD.save(_deps.storage, &threshold)?;
+    }
+    for (id, milestone) in (1u64..).zip(milestones) {
+        MILESTONES.save(_deps.storage, id, &milestone)?;
+    }
+
+    // the pool is the only minter, nobody can mint shares around it
+    let share_token = msg
+        .share_token
+        .map(|token| -> StdResult<_> {
+            let init = Cw20InstantiateMsg {
+                name: token.name,
+                symbol: token.symbol,
+                decimals: token.decimals,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: _env.contract.address.to_string(),
+                    cap: None,
+                }),
+            };
+            let instantiate = WasmMsg::Instantiate {
+                admin: None,
+                code_id: token.code_id,
+                msg: to_binary(&init)?,
+                funds: vec![],
+                label: format!("{} shares", msg.title),
+            };
+            Ok(SubMsg::reply_on_success(instantiate, SHARE_TOKEN_REPLY_ID))
+        })
+        .transpose()?;
 
-    let _cfg = Config {admin:_deps.api.addr_validate(msg.admin.as_str())?, title: msg.title };
+    let _cfg = Config {
+        admin: _deps.api.addr_validate(msg.admin.as_str())?,
+        title: msg.title,
+        target: msg.target,
+        denom: msg.denom,
+        deadline: msg.deadline,
+        guardian: msg
+            .guardian
+            .map(|addr| _deps.api.addr_validate(&addr))
+            .transpose()?,
+    };
 
     
     CONFIG.save(_deps.storage, &_cfg)?;
-    Ok(Response::default())
+    if let Some(factory) = msg.factory {
+        let factory = _deps.api.addr_validate(&factory)?;
+        if factory != _info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        FACTORY.save(_deps.storage, &factory)?;
+    }
+    if let Some(tokens_per_weight) = msg.tokens_per_weight {
+        if tokens_per_weight.is_zero() {
+            return Err(ContractError::ZeroTokensPerWeight {});
+        }
+        TOKENS_PER_WEIGHT.save(_deps.storage, &tokens_per_weight)?;
+    }
+    RAISED.save(_deps.storage, &Uint128::zero())?;
+    STATUS.save(_deps.storage, &PoolStatus::Open)?;
+    TOTAL.save(_deps.storage, &0, _env.block.height)?;
+    Ok(Response::new().add_submessages(share_token))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -39,21 +143,114 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::WithdrawFunds {recipient}=> execute_withdraw_funds(deps, env, info, recipient),
+        ExecuteMsg::WithdrawFunds {
+            recipient,
+            amount,
+            vesting,
+        } => execute_withdraw_funds(deps, env, info, recipient, amount, vesting),
+        ExecuteMsg::ClaimVested {} => execute_claim_vested(deps, env, info),
+        ExecuteMsg::CancelVesting { recipient } => {
+            execute_cancel_vesting(deps, env, info, recipient)
+        }
+        ExecuteMsg::Contribute { on_behalf_of } => execute_contribute(deps, env, info, on_behalf_of),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::Refund {} => execute_refund(deps, env, info),
+        ExecuteMsg::Cancel {} => execute_cancel(deps, env, info),
+        ExecuteMsg::Close {} => execute_close(deps, env, info),
+        ExecuteMsg::AddAllowedDenom { denom } => execute_add_allowed_denom(deps, info, denom),
+        ExecuteMsg::RemoveAllowedDenom { denom } => execute_remove_allowed_denom(deps, info, denom),
+        ExecuteMsg::UpdateBeneficiaries { beneficiaries } => {
+            execute_update_beneficiaries(deps, info, beneficiaries)
+        }
+        ExecuteMsg::VoteMilestone {
+            milestone_id,
+            approve,
+        } => execute_vote_milestone(deps, env, info, milestone_id, approve),
+        ExecuteMsg::ReleaseMilestone {
+            milestone_id,
+            recipient,
+        } => execute_release_milestone(deps, env, info, milestone_id, recipient),
+        ExecuteMsg::AddHook { addr } => execute_add_hook(deps, info, addr),
+        ExecuteMsg::RemoveHook { addr } => execute_remove_hook(deps, info, addr),
+        ExecuteMsg::AddContributionHook { addr } => {
+            execute_add_contribution_hook(deps, info, addr)
+        }
+        ExecuteMsg::RemoveContributionHook { addr } => {
+            execute_remove_contribution_hook(deps, info, addr)
+        }
+        ExecuteMsg::Pause {} => execute_pause(deps, info),
+        ExecuteMsg::Unpause {} => execute_unpause(deps, info),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config{}=>to_binary(&query_config(deps)?),
-        
+        QueryMsg::Status {} => to_binary(&query_status(deps, env)?),
+        QueryMsg::Contribution { address } => to_binary(&query_contribution(deps, address)?),
+        QueryMsg::ListContributions { start_after, limit } => {
+            to_binary(&query_list_contributions(deps, start_after, limit)?)
+        }
+        QueryMsg::ListAllowedDenoms { start_after, limit } => {
+            to_binary(&query_list_allowed_denoms(deps, start_after, limit)?)
+        }
+        QueryMsg::Withdrawn {} => to_binary(&query_withdrawn(deps)?),
+        QueryMsg::Beneficiaries {} => to_binary(&query_beneficiaries(deps)?),
+        QueryMsg::Milestones {} => to_binary(&query_milestones(deps, env)?),
+        QueryMsg::Admin {} => to_binary(&query_admin(deps)?),
+        QueryMsg::TotalWeight { at_height } => to_binary(&query_total_weight(deps, at_height)?),
+        QueryMsg::ListMembers { start_after, limit } => {
+            to_binary(&query_list_members(deps, start_after, limit)?)
+        }
+        QueryMsg::Member { addr, at_height } => to_binary(&query_member(deps, addr, at_height)?),
+        QueryMsg::Hooks {} => to_binary(&HOOKS.query_hooks(deps)?),
+        QueryMsg::ContributionHooks {} => to_binary(&CONTRIBUTION_HOOKS.query_hooks(deps)?),
+        QueryMsg::VestingStatus { recipient } => {
+            to_binary(&query_vesting_status(deps, env, recipient)?)
+        }
  }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(mut _deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    Ok(Response::new())
+pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match reply.id {
+        SHARE_TOKEN_REPLY_ID => Ok(handle_share_token_reply(deps, reply)?),
+        CONTRIBUTION_HOOK_REPLY_ID => Ok(handle_contribution_hook_reply(reply)),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let stored = get_contract_version(deps.storage)?;
+    let storage_version: Version = stored.version.parse()?;
+
+    // First, ensure we are working from an equal or older version of this contract
+    // wrong type
+    if stored.contract != CONTRACT_NAME && stored.contract != LEGACY_CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    // existing one is newer
+    if storage_version > version {
+        return Err(ContractError::CannotMigrateVersion {
+            previous_version: stored.version,
+        });
+    }
+
+    // legacy pools may still hold the original config without a funding target
+    if stored.contract == LEGACY_CONTRACT_NAME && CONFIG.load(deps.storage).is_err() {
+        v1::migrate_config(deps.branch(), &env, msg.funding)?;
+    }
+
+    // update contract version
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", stored.version))
 }
 
 
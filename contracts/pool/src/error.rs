@@ -1,12 +1,11 @@
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
-use cosmwasm_std::StdError;
-use cw_utils::PaymentError;
+use cosmwasm_std::{StdError, Uint128};
+use cw_controllers::HookError;
+use cw_utils::{PaymentError, ThresholdError};
 
-/// Never is a placeholder to ensure we don't return any errors
-#[derive(Error, Debug)]
-pub enum Never {}
+use crate::state::{MilestoneStatus, PoolStatus};
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
@@ -16,17 +15,122 @@ pub enum ContractError {
     #[error("{0}")]
     Payment(#[from] PaymentError),
 
+    #[error("{0}")]
+    Threshold(#[from] ThresholdError),
+
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
     #[error("Didn't send any funds")]
     NoFunds {},
 
     #[error("Only admin can do this")]
     Unauthorized,
 
+    #[error("Got a submessage reply with unknown id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Only pool shares can be redeemed")]
+    NotShareToken {},
+
     #[error("Recipient format is invalid")]
     InvalidFormat{recipient: String},
 
     #[error("Contract balance is empty")]
-    EmptyBalance
+    EmptyBalance,
+
+    #[error("Target must be greater than zero")]
+    ZeroTarget {},
+
+    #[error("Tokens per weight must be positive")]
+    ZeroTokensPerWeight {},
+
+    #[error("Deadline is already expired")]
+    InvalidDeadline {},
+
+    #[error("Pool is {status}, this can only be done when it is {expected}")]
+    InvalidStatus {
+        expected: PoolStatus,
+        status: PoolStatus,
+    },
+
+    #[error("Denom {denom} is not accepted by this pool")]
+    InvalidDenom { denom: String },
+
+    #[error("The target denom cannot be removed from the allow list")]
+    CannotRemoveTargetDenom {},
+
+    #[error("Only {available} {denom} available")]
+    InsufficientBalance { denom: String, available: Uint128 },
+
+    #[error("Beneficiary shares add up to {total} instead of 10000 basis points")]
+    InvalidShares { total: u64 },
+
+    #[error("Beneficiary {address} is listed more than once")]
+    DuplicateBeneficiary { address: String },
+
+    #[error("This pool pays out to its beneficiaries, no recipient can be given")]
+    BeneficiariesConfigured {},
+
+    #[error("A recipient is required when the pool has no beneficiaries")]
+    NoRecipient {},
+
+    #[error("Milestone amounts must be greater than zero and add up to the target")]
+    InvalidMilestones {},
+
+    #[error("A threshold is required to approve milestones")]
+    ThresholdRequired {},
+
+    #[error("Milestone {id} not found")]
+    MilestoneNotFound { id: u64 },
+
+    #[error("Milestone is {status}, this can only be done when it is {expected}")]
+    InvalidMilestoneStatus {
+        expected: MilestoneStatus,
+        status: MilestoneStatus,
+    },
+
+    #[error("Only contributors of the target denom can vote")]
+    NoVotingWeight {},
+
+    #[error("Already voted on this milestone")]
+    AlreadyVoted {},
+
+    #[error("Funds are released through milestones until all of them are")]
+    MilestonesPending {},
+
+    #[error("Nothing to refund")]
+    NothingToRefund {},
+
+    #[error("Contributions are paused")]
+    Paused {},
+
+    #[error("Vesting must end after it starts, with a cliff in between, all heights or all times")]
+    InvalidVesting {},
+
+    #[error("Nothing vested to claim")]
+    NothingToClaim {},
+
+    #[error("No vesting streams for {recipient}")]
+    NoStreams { recipient: String },
+
+    #[error("{0}")]
+    SemVer(String),
+
+    #[error("Cannot migrate from different contract type: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from unsupported version: {previous_version}")]
+    CannotMigrateVersion { previous_version: String },
+
+    #[error("Migrating a pool without a funding target requires `funding`")]
+    FundingRequired {},
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
 
 impl From<FromUtf8Error> for ContractError {
@@ -1,45 +1,1130 @@
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, BankMsg};
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Api, BlockInfo, Coin, CosmosMsg, Deps, DepsMut, Empty, Env,
+    MessageInfo, Order, Response, BankMsg, StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw4::{MemberChangedHookMsg, MemberDiff};
+use cw_utils::{nonpayable, one_coin, Expiration, Threshold};
 
-use crate::{ContractError, state::CONFIG};
+use crate::{
+    amount::{validate_denom, Amount},
+    contract::{CONTRIBUTION_HOOK_GAS_LIMIT, CONTRIBUTION_HOOK_REPLY_ID},
+    msg::{
+        Beneficiary, ContributionChange, ContributionHookMsg, MilestoneMsg, ReceiveMsg, VestingMsg,
+    },
+    state::{
+        load_admin, load_guardian, load_status, next_stream_id, progress, BeneficiaryVerified,
+        Milestone, MilestoneStatus, PoolStatus, Stream, ALLOW_LIST, BALLOTS, BENEFICIARIES, CONFIG,
+        CONTRIBUTIONS, CONTRIBUTION_HOOKS, FACTORY, HOOKS, MEMBERS, MILESTONES,
+        MILESTONE_THRESHOLD, PAUSED, RAISED, SHARE_TOKEN, STATUS, STREAMS, TOKENS_PER_WEIGHT,
+        TOTAL, TOTAL_SHARES, VESTING, WITHDRAWN,
+    },
+    ContractError,
+};
 
+fn ensure_admin(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if *sender != load_admin(deps)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn ensure_status(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    expected: PoolStatus,
+) -> Result<(), ContractError> {
+    let status = load_status(storage, block)?;
+    if status != expected {
+        return Err(ContractError::InvalidStatus { expected, status });
+    }
+    Ok(())
+}
+
+/// Balances of every cw20 token the pool accepts, skipping empty ones
+fn cw20_balances(deps: Deps, env: &Env) -> StdResult<Vec<Amount>> {
+    let denoms = ALLOW_LIST
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut balances = vec![];
+    for denom in denoms {
+        let token = match Amount::from_parts(denom, Default::default()) {
+            Amount::Cw20(token) => token,
+            Amount::Native(_) => continue,
+        };
+        let query = Cw20QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+        };
+        let res: BalanceResponse = deps.querier.query_wasm_smart(&token.address, &query)?;
+        let amount = res.balance;
+        if !amount.is_zero() {
+            balances.push(Amount::Cw20(Cw20Coin {
+                address: token.address,
+                amount,
+            }));
+        }
+    }
+    Ok(balances)
+}
+
+
+/// Current balance of the pool in the given native or `cw20:<contract>` denom
+pub(crate) fn balance_of(deps: Deps, env: &Env, denom: &str) -> StdResult<Uint128> {
+    match denom.strip_prefix("cw20:") {
+        Some(address) => {
+            let query = Cw20QueryMsg::Balance {
+                address: env.contract.address.to_string(),
+            };
+            let res: BalanceResponse = deps.querier.query_wasm_smart(address, &query)?;
+            Ok(res.balance)
+        }
+        None => Ok(deps.querier.query_balance(&env.contract.address, denom)?.amount),
+    }
+}
+
+/// What the streams still hold back of `denom`
+fn vesting_of(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    Ok(VESTING.may_load(storage, denom)?.unwrap_or_default())
+}
+
+/// Checks every requested coin against the pool balance, skipping zero amounts
+fn requested_amounts(
+    deps: Deps,
+    env: &Env,
+    amount: Vec<Coin>,
+) -> Result<Vec<Amount>, ContractError> {
+    let mut withdrawals = vec![];
+    for coin in amount.into_iter().filter(|coin| !coin.amount.is_zero()) {
+        let available = balance_of(deps, env, &coin.denom)?
+            .saturating_sub(vesting_of(deps.storage, &coin.denom)?);
+        if available < coin.amount {
+            return Err(ContractError::InsufficientBalance {
+                denom: coin.denom,
+                available,
+            });
+        }
+        withdrawals.push(Amount::from_parts(coin.denom, coin.amount));
+    }
+    Ok(withdrawals)
+}
+
+/// Validates beneficiary addresses and makes sure the shares add up to the whole, an empty list is fine
+pub fn validate_beneficiaries(
+    api: &dyn Api,
+    beneficiaries: Vec<Beneficiary>,
+) -> Result<Vec<BeneficiaryVerified>, ContractError> {
+    if beneficiaries.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut verified: Vec<BeneficiaryVerified> = vec![];
+    for beneficiary in beneficiaries {
+        let address = api.addr_validate(&beneficiary.address)?;
+        if verified.iter().any(|other| other.address == address) {
+            return Err(ContractError::DuplicateBeneficiary {
+                address: address.into(),
+            });
+        }
+        verified.push(BeneficiaryVerified {
+            address,
+            share: beneficiary.share,
+        });
+    }
+    let total: u64 = verified.iter().map(|b| b.share as u64).sum();
+    if total != TOTAL_SHARES as u64 || verified.iter().any(|b| b.share == 0) {
+        return Err(ContractError::InvalidShares { total });
+    }
+    Ok(verified)
+}
+
+/// Splits `amount` by the payee shares, rounding down. The first payee gets the dust so
+/// the parts always add up to `amount`.
+fn split(amount: Uint128, payees: &[BeneficiaryVerified]) -> Vec<Uint128> {
+    let mut parts: Vec<_> = payees
+        .iter()
+        .map(|payee| amount.multiply_ratio(payee.share, TOTAL_SHARES))
+        .collect();
+    let dust = amount - parts.iter().sum::<Uint128>();
+    parts[0] += dust;
+    parts
+}
+
+/// Who a withdrawal goes to: the beneficiaries if the pool has any, `recipient` otherwise
+fn payees(
+    deps: Deps,
+    recipient: Option<String>,
+) -> Result<Vec<BeneficiaryVerified>, ContractError> {
+    let beneficiaries = BENEFICIARIES.load(deps.storage)?;
+    match (recipient, beneficiaries.is_empty()) {
+        (Some(_), false) => Err(ContractError::BeneficiariesConfigured {}),
+        (None, true) => Err(ContractError::NoRecipient {}),
+        (Some(recipient), true) => Ok(vec![BeneficiaryVerified {
+            address: deps.api.addr_validate(recipient.as_str())?,
+            share: TOTAL_SHARES,
+        }]),
+        (None, false) => Ok(beneficiaries),
+    }
+}
+
+/// Records the withdrawals and splits them between the payees. Every payee gets their native
+/// coins in a single bank message, and a transfer per token.
+fn payout(
+    storage: &mut dyn Storage,
+    withdrawals: Vec<Amount>,
+    payees: &[BeneficiaryVerified],
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut funds = vec![vec![]; payees.len()];
+    let mut transfers = vec![vec![]; payees.len()];
+    for withdrawal in withdrawals {
+        record_withdrawal(storage, &withdrawal)?;
+        let parts = split(withdrawal.amount(), payees);
+        for (i, part) in parts.into_iter().enumerate() {
+            if part.is_zero() {
+                continue;
+            }
+            match Amount::from_parts(withdrawal.denom(), part) {
+                Amount::Native(coin) => funds[i].push(coin),
+                token => transfers[i].push(token.into_msg(payees[i].address.to_string())?),
+            }
+        }
+    }
+    let mut messages = vec![];
+    for ((payee, funds), transfers) in payees.iter().zip(funds).zip(transfers) {
+        if !funds.is_empty() {
+            messages.push(
+                BankMsg::Send {
+                    to_address: payee.address.to_string(),
+                    amount: funds,
+                }
+                .into(),
+            );
+        }
+        messages.extend(transfers);
+    }
+    Ok(messages)
+}
+
+fn record_withdrawal(storage: &mut dyn Storage, withdrawal: &Amount) -> StdResult<()> {
+    WITHDRAWN.update(storage, &withdrawal.denom(), |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() + withdrawal.amount())
+    })?;
+    Ok(())
+}
+
+fn same_unit(a: &Expiration, b: &Expiration) -> bool {
+    matches!(
+        (a, b),
+        (Expiration::AtHeight(_), Expiration::AtHeight(_))
+            | (Expiration::AtTime(_), Expiration::AtTime(_))
+    )
+}
+
+/// Vesting has to end after it starts, any cliff lies in between, all in the same unit
+fn validate_vesting(block: &BlockInfo, vesting: &VestingMsg) -> Result<(), ContractError> {
+    let (start, end) = match (progress(&vesting.start, block), progress(&vesting.end, block)) {
+        (Some((start, _)), Some((end, _))) if same_unit(&vesting.start, &vesting.end) => {
+            (start, end)
+        }
+        _ => return Err(ContractError::InvalidVesting {}),
+    };
+    if start >= end {
+        return Err(ContractError::InvalidVesting {});
+    }
+    if let Some(cliff) = vesting.cliff.as_ref() {
+        match progress(cliff, block) {
+            Some((cliff_at, _)) if same_unit(cliff, &vesting.start) => {
+                if cliff_at < start || cliff_at > end {
+                    return Err(ContractError::InvalidVesting {});
+                }
+            }
+            _ => return Err(ContractError::InvalidVesting {}),
+        }
+    }
+    Ok(())
+}
+
+/// Records the withdrawals like `payout`, but holds them back in a stream per payee and denom
+fn stream(
+    storage: &mut dyn Storage,
+    withdrawals: Vec<Amount>,
+    payees: &[BeneficiaryVerified],
+    vesting: VestingMsg,
+) -> StdResult<()> {
+    for withdrawal in withdrawals {
+        record_withdrawal(storage, &withdrawal)?;
+        VESTING.update(storage, &withdrawal.denom(), |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default() + withdrawal.amount())
+        })?;
+        let parts = split(withdrawal.amount(), payees);
+        for (payee, amount) in payees.iter().zip(parts) {
+            if amount.is_zero() {
+                continue;
+            }
+            let stream = Stream {
+                denom: withdrawal.denom(),
+                amount,
+                claimed: Uint128::zero(),
+                start: vesting.start,
+                end: vesting.end,
+                cliff: vesting.cliff,
+            };
+            let id = next_stream_id(storage)?;
+            STREAMS.save(storage, (&payee.address, id), &stream)?;
+        }
+    }
+    Ok(())
+}
+
+fn addresses(payees: &[BeneficiaryVerified]) -> String {
+    payees
+        .iter()
+        .map(|payee| payee.address.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// What every payee gets of each withdrawal, split like `payout` and `stream` do
+fn parts(withdrawals: &[Amount], payees: &[BeneficiaryVerified]) -> Vec<(Addr, Amount)> {
+    let mut parts = vec![];
+    for withdrawal in withdrawals {
+        for (payee, part) in payees.iter().zip(split(withdrawal.amount(), payees)) {
+            if !part.is_zero() {
+                parts.push((
+                    payee.address.clone(),
+                    Amount::from_parts(withdrawal.denom(), part),
+                ));
+            }
+        }
+    }
+    parts
+}
+
+/// Notifies the contribution hooks of every transfer. `outgoing` transfers are only sent after
+/// this, so the hooks get the balance without them.
+fn contribution_hooks(
+    deps: Deps,
+    env: &Env,
+    change: ContributionChange,
+    transfers: &[(Addr, Amount)],
+    outgoing: bool,
+) -> StdResult<Vec<SubMsg>> {
+    // spare the balance queries if nobody listens
+    if CONTRIBUTION_HOOKS.query_hooks(deps)?.hooks.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut hooks = vec![];
+    for (account, amount) in transfers {
+        let denom = amount.denom();
+        let leaving: Uint128 = match outgoing {
+            true => transfers
+                .iter()
+                .filter(|(_, other)| other.denom() == denom)
+                .map(|(_, other)| other.amount())
+                .sum(),
+            false => Uint128::zero(),
+        };
+        let pool_total = balance_of(deps, env, &denom)?
+            .saturating_sub(leaving)
+            .saturating_sub(vesting_of(deps.storage, &denom)?);
+        let msg = ContributionHookMsg {
+            change,
+            contributor: account.to_string(),
+            amount: amount.amount(),
+            denom,
+            pool_total,
+        };
+        hooks.extend(CONTRIBUTION_HOOKS.prepare_hooks(deps.storage, |hook| {
+            let msg = msg.clone().into_cosmos_msg(hook)?;
+            Ok(SubMsg::reply_on_error(msg, CONTRIBUTION_HOOK_REPLY_ID)
+                .with_gas_limit(CONTRIBUTION_HOOK_GAS_LIMIT))
+        })?);
+    }
+    Ok(hooks)
+}
 
 pub fn execute_withdraw_funds(
     _deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
-    recipient: String,
+    recipient: Option<String>,
+    amount: Option<Vec<Coin>>,
+    vesting: Option<VestingMsg>,
 ) -> Result<Response, ContractError> {
     
-    let cfg = CONFIG.load(_deps.storage)?;
+    ensure_admin(_deps.as_ref(), &_info.sender)?;
+    if let Some(vesting) = vesting.as_ref() {
+        validate_vesting(&_env.block, vesting)?;
+    }
+    ensure_status(_deps.storage, &_env.block, PoolStatus::Succeeded)?;
+    // milestone pools pay out tranche by tranche, the rest can only go once they are all released
+    ensure_milestones_released(_deps.storage)?;
+
+    let payees = payees(_deps.as_ref(), recipient)?;
+
+    // everything we hold unless told otherwise, minus what is still vesting
+    let withdrawals = match amount {
+        Some(amount) => requested_amounts(_deps.as_ref(), &_env, amount)?,
+        None => {
+            let funds = _deps.querier.query_all_balances(&_env.contract.address)?;
+            let mut balances: Vec<_> = funds.into_iter().map(Amount::Native).collect();
+            balances.extend(cw20_balances(_deps.as_ref(), &_env)?);
+            let mut withdrawals = vec![];
+            for balance in balances {
+                let vesting = vesting_of(_deps.storage, &balance.denom())?;
+                let amount = balance.amount().saturating_sub(vesting);
+                if !amount.is_zero() {
+                    withdrawals.push(Amount::from_parts(balance.denom(), amount));
+                }
+            }
+            withdrawals
+        }
+    };
+    if withdrawals.is_empty() {
+        return Err(ContractError::EmptyBalance {});
+    }
+    let reported = withdrawals
+        .iter()
+        .map(|withdrawal| withdrawal.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let transfers = parts(&withdrawals, &payees);
+    let vested = vesting.is_some();
+    let messages = match vesting {
+        Some(vesting) => {
+            stream(_deps.storage, withdrawals, &payees, vesting)?;
+            vec![]
+        }
+        None => payout(_deps.storage, withdrawals, &payees)?,
+    };
+    // streamed funds stay in the pool, held back as vesting
+    let hooks = contribution_hooks(
+        _deps.as_ref(),
+        &_env,
+        ContributionChange::Withdrawn,
+        &transfers,
+        !vested,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_attributes(vec![
+            ("action", "withdraw_funds"),
+            ("recipient", &addresses(&payees)),
+            ("amount", &reported),
+            ("vesting", &vested.to_string()),
+        ]))
+}
+
+
+pub fn execute_contribute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    on_behalf_of: Option<String>,
+) -> Result<Response, ContractError> {
+    let coin = one_coin(&info)?;
+    let contributor = contributor(deps.as_ref(), info.sender, on_behalf_of)?;
+    contribute(deps, env, contributor, Amount::Native(coin))
+}
 
-    if _info.sender != cfg.admin {
+/// Who a contribution is recorded against, only the factory can forward someone else's
+fn contributor(
+    deps: Deps,
+    sender: Addr,
+    on_behalf_of: Option<String>,
+) -> Result<Addr, ContractError> {
+    match on_behalf_of {
+        Some(contributor) => {
+            if FACTORY.may_load(deps.storage)?.as_ref() != Some(&sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            Ok(deps.api.addr_validate(&contributor)?)
+        }
+        None => Ok(sender),
+    }
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    let amount = Amount::Cw20(Cw20Coin {
+        address: info.sender.to_string(),
+        amount: wrapper.amount,
+    });
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+    match msg {
+        ReceiveMsg::Contribute { on_behalf_of } => {
+            let contributor = contributor(deps.as_ref(), sender, on_behalf_of)?;
+            contribute(deps, env, contributor, amount)
+        }
+        ReceiveMsg::Redeem {} => redeem(deps, env, info.sender, sender, wrapper.amount),
+    }
+}
+
+/// Burns the `shares` sent by `holder` and pays them the same fraction of every balance,
+/// leaving what the streams hold back
+fn redeem(
+    deps: DepsMut,
+    env: Env,
+    token: Addr,
+    holder: Addr,
+    shares: Uint128,
+) -> Result<Response, ContractError> {
+    if SHARE_TOKEN.may_load(deps.storage)?.as_ref() != Some(&token) {
+        return Err(ContractError::NotShareToken {});
+    }
+    ensure_status(deps.storage, &env.block, PoolStatus::Closed)?;
+
+    // the supply still counts the shares we got sent
+    let info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&token, &Cw20QueryMsg::TokenInfo {})?;
+    let funds = deps.querier.query_all_balances(&env.contract.address)?;
+    let mut balances: Vec<_> = funds.into_iter().map(Amount::Native).collect();
+    balances.extend(cw20_balances(deps.as_ref(), &env)?);
+
+    let mut funds = vec![];
+    let mut messages = vec![];
+    let mut redeemed = vec![];
+    let mut transfers = vec![];
+    for balance in balances {
+        let available = balance
+            .amount()
+            .saturating_sub(vesting_of(deps.storage, &balance.denom())?);
+        let slice = Amount::from_parts(
+            balance.denom(),
+            available.multiply_ratio(shares, info.total_supply),
+        );
+        if slice.is_empty() {
+            continue;
+        }
+        redeemed.push(slice.to_string());
+        transfers.push((holder.clone(), slice.clone()));
+        match slice {
+            Amount::Native(coin) => funds.push(coin),
+            token => messages.push(token.into_msg(holder.to_string())?),
+        }
+    }
+    if !funds.is_empty() {
+        messages.insert(
+            0,
+            BankMsg::Send {
+                to_address: holder.to_string(),
+                amount: funds,
+            }
+            .into(),
+        );
+    }
+    messages.push(
+        WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount: shares })?,
+            funds: vec![],
+        }
+        .into(),
+    );
+    let amount = match redeemed.is_empty() {
+        true => "none".to_string(),
+        false => redeemed.join(","),
+    };
+    let change = ContributionChange::Redeemed;
+    let hooks = contribution_hooks(deps.as_ref(), &env, change, &transfers, true)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_attributes(vec![
+            ("action", "redeem"),
+            ("holder", holder.as_str()),
+            ("shares", &shares.to_string()),
+            ("amount", &amount),
+        ]))
+}
+
+/// Mints shares 1:1 for a target denom contribution, if the pool has a share token
+fn mint_shares(
+    storage: &dyn Storage,
+    contributor: &Addr,
+    amount: Uint128,
+) -> StdResult<Option<WasmMsg>> {
+    SHARE_TOKEN
+        .may_load(storage)?
+        .map(|token| -> StdResult<_> {
+            let mint = Cw20ExecuteMsg::Mint {
+                recipient: contributor.to_string(),
+                amount,
+            };
+            Ok(WasmMsg::Execute {
+                contract_addr: token.into(),
+                msg: to_binary(&mint)?,
+                funds: vec![],
+            })
+        })
+        .transpose()
+}
+
+/// Syncs the member weight with the target denom contribution, like cw4-stake does with the stake.
+/// Returns the hook messages to send if it changed.
+pub(crate) fn update_membership(
+    storage: &mut dyn Storage,
+    contributor: &Addr,
+    height: u64,
+) -> StdResult<Vec<SubMsg>> {
+    let cfg = CONFIG.load(storage)?;
+    let tokens_per_weight = TOKENS_PER_WEIGHT
+        .may_load(storage)?
+        .unwrap_or_else(Uint128::one);
+    // capped rather than failing the contribution, the view is only as precise as a u64 allows
+    let new = CONTRIBUTIONS
+        .may_load(storage, (contributor, cfg.denom.as_str()))?
+        .map(|amount| u64::try_from((amount / tokens_per_weight).u128()).unwrap_or(u64::MAX));
+    let old = MEMBERS.may_load(storage, contributor)?;
+    if new == old {
+        return Ok(vec![]);
+    }
+
+    match new.as_ref() {
+        Some(weight) => MEMBERS.save(storage, contributor, weight, height),
+        None => MEMBERS.remove(storage, contributor, height),
+    }?;
+    let total = TOTAL.may_load(storage)?.unwrap_or_default();
+    let total = total
+        .saturating_sub(old.unwrap_or_default())
+        .saturating_add(new.unwrap_or_default());
+    TOTAL.save(storage, &total, height)?;
+
+    let diff = MemberDiff::new(contributor, old, new);
+    HOOKS.prepare_hooks(storage, |hook| {
+        MemberChangedHookMsg::one(diff.clone())
+            .into_cosmos_msg(hook)
+            .map(SubMsg::new)
+    })
+}
+
+fn contribute(
+    deps: DepsMut,
+    env: Env,
+    contributor: Addr,
+    amount: Amount,
+) -> Result<Response, ContractError> {
+    ensure_status(deps.storage, &env.block, PoolStatus::Open)?;
+    if PAUSED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::Paused {});
+    }
+    let cfg = CONFIG.load(deps.storage)?;
+    let denom = amount.denom();
+    if !ALLOW_LIST.has(deps.storage, &denom) {
+        return Err(ContractError::InvalidDenom { denom });
+    }
+    if amount.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+
+    CONTRIBUTIONS.update(
+        deps.storage,
+        (&contributor, denom.as_str()),
+        |total| -> StdResult<_> { Ok(total.unwrap_or_default() + amount.amount()) },
+    )?;
+    // only the target denom counts towards the target
+    let raised = RAISED.update(deps.storage, |raised| -> StdResult<_> {
+        match denom == cfg.denom {
+            true => Ok(raised + amount.amount()),
+            false => Ok(raised),
+        }
+    })?;
+
+    let mut status = PoolStatus::Open;
+    if raised >= cfg.target {
+        status = PoolStatus::Succeeded;
+        STATUS.save(deps.storage, &status)?;
+    }
+    let hooks = update_membership(deps.storage, &contributor, env.block.height)?;
+    let mint = match denom == cfg.denom {
+        true => mint_shares(deps.storage, &contributor, amount.amount())?,
+        false => None,
+    };
+    let transfers = [(contributor.clone(), amount.clone())];
+    let change = ContributionChange::Contributed;
+    let notifications = contribution_hooks(deps.as_ref(), &env, change, &transfers, false)?;
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_messages(mint)
+        .add_submessages(notifications)
+        .add_attributes(vec![
+            ("action", "contribute"),
+            ("contributor", contributor.as_str()),
+            ("amount", &amount.to_string()),
+            ("raised", &raised.to_string()),
+            ("status", &status.to_string()),
+        ]))
+}
+
+pub fn execute_refund(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ensure_status(deps.storage, &env.block, PoolStatus::Failed)?;
+    STATUS.save(deps.storage, &PoolStatus::Failed)?;
+
+    let refund = CONTRIBUTIONS
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Amount::from_parts(denom, amount)))
+        .collect::<StdResult<Vec<_>>>()?;
+    if refund.is_empty() {
+        return Err(ContractError::NothingToRefund {});
+    }
+
+    // tranches already released are gone, what is left of the target denom is shared pro rata
+    let cfg = CONFIG.load(deps.storage)?;
+    let raised = RAISED.load(deps.storage)?;
+    let released = MILESTONES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|(_, milestone)| milestone.released)
+        .map(|(_, milestone)| milestone.amount)
+        .sum::<Uint128>();
+
+    let mut messages = vec![];
+    let mut refunded = vec![];
+    let mut transfers = vec![];
+    for amount in refund {
+        CONTRIBUTIONS.remove(deps.storage, (&info.sender, amount.denom().as_str()));
+        let amount = match amount.denom() == cfg.denom && !released.is_zero() {
+            true => Amount::from_parts(
+                amount.denom(),
+                amount.amount().multiply_ratio(raised - released, raised),
+            ),
+            false => amount,
+        };
+        if amount.is_empty() {
+            continue;
+        }
+        refunded.push(amount.to_string());
+        transfers.push((info.sender.clone(), amount.clone()));
+        messages.push(amount.into_msg(info.sender.to_string())?);
+    }
+    let amount = refunded.join(",");
+    let hooks = update_membership(deps.storage, &info.sender, env.block.height)?;
+    let change = ContributionChange::Refunded;
+    let notifications = contribution_hooks(deps.as_ref(), &env, change, &transfers, true)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_submessages(notifications)
+        .add_attributes(vec![
+            ("action", "refund"),
+            ("contributor", info.sender.as_str()),
+            ("amount", &amount),
+        ]))
+}
+
+pub fn execute_cancel(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    ensure_status(deps.storage, &env.block, PoolStatus::Open)?;
+    STATUS.save(deps.storage, &PoolStatus::Failed)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "cancel"),
+        ("status", &PoolStatus::Failed.to_string()),
+    ]))
+}
+
+fn ensure_milestones_released(storage: &dyn Storage) -> Result<(), ContractError> {
+    for item in MILESTONES.range(storage, None, None, Order::Ascending) {
+        let (_, milestone) = item?;
+        if !milestone.released {
+            return Err(ContractError::MilestonesPending {});
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_close(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    ensure_status(deps.storage, &env.block, PoolStatus::Succeeded)?;
+    // closing would lock the tranches contributors still have to vote on
+    ensure_milestones_released(deps.storage)?;
+    STATUS.save(deps.storage, &PoolStatus::Closed)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "close"),
+        ("status", &PoolStatus::Closed.to_string()),
+    ]))
+}
+
+pub fn execute_add_allowed_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    validate_denom(deps.api, &denom)?;
+    ALLOW_LIST.save(deps.storage, &denom, &Empty {})?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "add_allowed_denom"),
+        ("denom", &denom),
+    ]))
+}
+
+pub fn execute_remove_allowed_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    if denom == cfg.denom {
+        return Err(ContractError::CannotRemoveTargetDenom {});
+    }
+    ALLOW_LIST.remove(deps.storage, &denom);
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "remove_allowed_denom"),
+        ("denom", &denom),
+    ]))
+}
+
+pub fn execute_update_beneficiaries(
+    deps: DepsMut,
+    info: MessageInfo,
+    beneficiaries: Vec<Beneficiary>,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    let beneficiaries = validate_beneficiaries(deps.api, beneficiaries)?;
+    BENEFICIARIES.save(deps.storage, &beneficiaries)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_beneficiaries"),
+        ("beneficiaries", &beneficiaries.len().to_string()),
+    ]))
+}
+
+fn load_milestone(storage: &dyn Storage, id: u64) -> Result<Milestone, ContractError> {
+    MILESTONES
+        .may_load(storage, id)?
+        .ok_or(ContractError::MilestoneNotFound { id })
+}
+
+/// Current status of the milestone, erroring unless it is `expected`
+fn ensure_milestone_status(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    milestone: &Milestone,
+    expected: MilestoneStatus,
+) -> Result<(), ContractError> {
+    let threshold = MILESTONE_THRESHOLD.load(storage)?;
+    let total = RAISED.load(storage)?;
+    let status = milestone.current_status(block, &threshold, total);
+    if status != expected {
+        return Err(ContractError::InvalidMilestoneStatus { expected, status });
+    }
+    Ok(())
+}
+
+pub fn execute_vote_milestone(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    milestone_id: u64,
+    approve: bool,
+) -> Result<Response, ContractError> {
+    ensure_status(deps.storage, &env.block, PoolStatus::Succeeded)?;
+    let mut milestone = load_milestone(deps.storage, milestone_id)?;
+    ensure_milestone_status(deps.storage, &env.block, &milestone, MilestoneStatus::Voting)?;
+
+    // weighted by what they put in towards the target
+    let cfg = CONFIG.load(deps.storage)?;
+    let weight = CONTRIBUTIONS
+        .may_load(deps.storage, (&info.sender, cfg.denom.as_str()))?
+        .unwrap_or_default();
+    if weight.is_zero() {
+        return Err(ContractError::NoVotingWeight {});
+    }
+    if BALLOTS.has(deps.storage, (milestone_id, &info.sender)) {
+        return Err(ContractError::AlreadyVoted {});
+    }
+    BALLOTS.save(deps.storage, (milestone_id, &info.sender), &approve)?;
+
+    match approve {
+        true => milestone.yes += weight,
+        false => milestone.no += weight,
+    }
+    MILESTONES.save(deps.storage, milestone_id, &milestone)?;
+
+    let threshold = MILESTONE_THRESHOLD.load(deps.storage)?;
+    let total = RAISED.load(deps.storage)?;
+    let status = milestone.current_status(&env.block, &threshold, total);
+    Ok(Response::new().add_attributes(vec![
+        ("action", "vote_milestone"),
+        ("milestone_id", &milestone_id.to_string()),
+        ("voter", info.sender.as_str()),
+        ("approve", &approve.to_string()),
+        ("status", &status.to_string()),
+    ]))
+}
+
+pub fn execute_release_milestone(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    milestone_id: u64,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_status(deps.storage, &env.block, PoolStatus::Succeeded)?;
+    let mut milestone = load_milestone(deps.storage, milestone_id)?;
+    ensure_milestone_status(deps.storage, &env.block, &milestone, MilestoneStatus::Passed)?;
+
+    let payees = payees(deps.as_ref(), recipient)?;
+    let tranche = requested_amounts(
+        deps.as_ref(),
+        &env,
+        vec![Coin {
+            denom: cfg.denom,
+            amount: milestone.amount,
+        }],
+    )?;
+    let reported = tranche[0].to_string();
+    let transfers = parts(&tranche, &payees);
+    let messages = payout(deps.storage, tranche, &payees)?;
+
+    milestone.released = true;
+    MILESTONES.save(deps.storage, milestone_id, &milestone)?;
+    let change = ContributionChange::Withdrawn;
+    let hooks = contribution_hooks(deps.as_ref(), &env, change, &transfers, true)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_attributes(vec![
+            ("action", "release_milestone"),
+            ("milestone_id", &milestone_id.to_string()),
+            ("recipient", &addresses(&payees)),
+            ("amount", &reported),
+        ]))
+}
+
+/// Checks the milestones add up to the target and turns them into their stored form. They are
+/// voted on once the target is raised, so their deadlines come after the funding `deadline`.
+pub fn validate_milestones(
+    deadline: &Expiration,
+    target: Uint128,
+    milestones: Vec<MilestoneMsg>,
+    threshold: Option<Threshold>,
+) -> Result<Vec<Milestone>, ContractError> {
+    if milestones.is_empty() {
+        return Ok(vec![]);
+    }
+    let threshold = threshold.ok_or(ContractError::ThresholdRequired {})?;
+    // the total weight is whatever gets raised, at least the target
+    threshold.validate(target.u128().try_into().unwrap_or(u64::MAX))?;
+
+    let total: Uint128 = milestones.iter().map(|milestone| milestone.amount).sum();
+    if total != target || milestones.iter().any(|milestone| milestone.amount.is_zero()) {
+        return Err(ContractError::InvalidMilestones {});
+    }
+    // an expired milestone counts as rejected, failing the pool the moment it succeeds
+    if milestones.iter().any(|milestone| {
+        !same_unit(&milestone.deadline, deadline)
+            || milestone.deadline.partial_cmp(deadline) != Some(std::cmp::Ordering::Greater)
+    }) {
+        return Err(ContractError::InvalidDeadline {});
+    }
+
+    Ok(milestones
+        .into_iter()
+        .map(|milestone| Milestone {
+            description: milestone.description,
+            amount: milestone.amount,
+            deadline: milestone.deadline,
+            released: false,
+            yes: Uint128::zero(),
+            no: Uint128::zero(),
+        })
+        .collect())
+}
+
+pub fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    HOOKS.add_hook(deps.storage, deps.api.addr_validate(&addr)?)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "add_hook"), ("hook", &addr)]))
+}
+
+pub fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    HOOKS.remove_hook(deps.storage, deps.api.addr_validate(&addr)?)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "remove_hook"), ("hook", &addr)]))
+}
+
+pub fn execute_add_contribution_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    CONTRIBUTION_HOOKS.add_hook(deps.storage, deps.api.addr_validate(&addr)?)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "add_contribution_hook"),
+        ("hook", &addr),
+    ]))
+}
+
+pub fn execute_remove_contribution_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    CONTRIBUTION_HOOKS.remove_hook(deps.storage, deps.api.addr_validate(&addr)?)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "remove_contribution_hook"),
+        ("hook", &addr),
+    ]))
+}
+
+pub fn execute_pause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    if Some(&info.sender) != load_guardian(deps.as_ref())?.as_ref() {
         return Err(ContractError::Unauthorized {});
     }
-    
-    let valid_addr = _deps.api.addr_validate(recipient.as_str())?;
+    PAUSED.save(deps.storage, &true)?;
 
+    Ok(Response::new().add_attributes(vec![
+        ("action", "pause"),
+        ("sender", info.sender.as_str()),
+    ]))
+}
 
-    let funds = _deps.querier.query_all_balances(_env.contract.address)?;
-    if funds.is_empty() {
+pub fn execute_unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    PAUSED.save(deps.storage, &false)?;
 
-        return Err(ContractError::EmptyBalance {});
+    Ok(Response::new().add_attributes(vec![
+        ("action", "unpause"),
+        ("sender", info.sender.as_str()),
+    ]))
+}
+
+pub fn execute_claim_vested(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let streams = STREAMS
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut claims = vec![];
+    for (id, mut stream) in streams {
+        let claimable = stream.vested(&env.block).saturating_sub(stream.claimed);
+        if claimable.is_zero() {
+            continue;
+        }
+        VESTING.update(deps.storage, &stream.denom, |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default().checked_sub(claimable)?)
+        })?;
+        stream.claimed += claimable;
+        match stream.claimed == stream.amount {
+            true => STREAMS.remove(deps.storage, (&info.sender, id)),
+            false => STREAMS.save(deps.storage, (&info.sender, id), &stream)?,
+        }
+        claims.push(Amount::from_parts(stream.denom, claimable));
     }
-    let balance =_deps.querier.query_all_balances(_env.contract.address)?;
-    
-    // send funds to recipient
-    let bank_message = BankMsg::Send {
-        to_address: valid_addr.to_string(),
-        amount: funds,
+    if claims.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    let amount = claims
+        .iter()
+        .map(|claim| claim.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let messages = claims
+        .into_iter()
+        .map(|claim| claim.into_msg(info.sender.to_string()))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "claim_vested"),
+        ("recipient", info.sender.as_str()),
+        ("amount", &amount),
+    ]))
+}
+
+pub fn execute_cancel_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info.sender)?;
+    let addr = deps.api.addr_validate(&recipient)?;
+    let streams = STREAMS
+        .prefix(&addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if streams.is_empty() {
+        return Err(ContractError::NoStreams { recipient });
+    }
+
+    // the recipient keeps what vested so far, the rest is back to being withdrawable
+    let mut returned = vec![];
+    for (id, mut stream) in streams {
+        let unvested = stream.cancel(&env.block);
+        for store in [VESTING, WITHDRAWN] {
+            store.update(deps.storage, &stream.denom, |total| -> StdResult<_> {
+                Ok(total.unwrap_or_default().checked_sub(unvested)?)
+            })?;
+        }
+        match stream.claimed == stream.amount {
+            true => STREAMS.remove(deps.storage, (&addr, id)),
+            false => STREAMS.save(deps.storage, (&addr, id), &stream)?,
+        }
+        if !unvested.is_zero() {
+            returned.push(Amount::from_parts(stream.denom, unvested).to_string());
+        }
+    }
+    let amount = match returned.is_empty() {
+        true => "none".to_string(),
+        false => returned.join(","),
     };
-    
-    Ok(Response::new().add_message(bank_message).add_attributes(vec![
-        ("action", "withdraw_funds"),
-        ("recipient", &valid_addr.to_string()),
 
-        // ("amount", &balance[0].into()),
-        // we want to know how much was withdrawn   
+    Ok(Response::new().add_attributes(vec![
+        ("action", "cancel_vesting"),
+        ("recipient", addr.as_str()),
+        ("amount", &amount),
     ]))
-    // send funds to recipient
-    
-    
 }
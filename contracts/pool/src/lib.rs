@@ -1,3 +1,4 @@
+pub mod amount;
 pub mod contract;
 mod error;
 
@@ -5,5 +6,11 @@ pub mod msg;
 pub mod state;
 pub mod execute;
 pub mod query;
+pub mod reply;
+pub mod helpers;
+pub mod migrations;
+
+#[cfg(test)]
+mod tests;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,74 @@
+// pools were deployed under this name before they got one of their own
+pub const LEGACY_CONTRACT_NAME: &str = "crates.io:cw20-ics20";
+
+// the original pool only knew its admin and title
+pub mod v1 {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{Addr, DepsMut, Empty, Env};
+    use cw_storage_plus::Item;
+
+    use crate::amount::validate_denom;
+    use crate::execute::{balance_of, update_membership};
+    use crate::msg::Funding;
+    use crate::state::{
+        Config, PoolStatus, ALLOW_LIST, BENEFICIARIES, CONFIG, CONTRIBUTIONS, FACTORY, RAISED,
+        STATUS, TOTAL,
+    };
+    use crate::ContractError;
+
+    pub const CONFIG_V1: Item<ConfigV1> = Item::new("config");
+
+    #[cw_serde]
+    pub struct ConfigV1 {
+        pub admin: Addr,
+        pub title: String,
+    }
+
+    /// Extends the original config with the funding settings and opens the pool. What it
+    /// already holds is recorded as contributed by the admin, who could withdraw it before, so
+    /// it can still be refunded if the pool fails.
+    pub fn migrate_config(
+        deps: DepsMut,
+        env: &Env,
+        funding: Option<Funding>,
+    ) -> Result<(), ContractError> {
+        let old = CONFIG_V1.load(deps.storage)?;
+        let funding = funding.ok_or(ContractError::FundingRequired {})?;
+        if funding.target.is_zero() {
+            return Err(ContractError::ZeroTarget {});
+        }
+        if funding.deadline.is_expired(&env.block) {
+            return Err(ContractError::InvalidDeadline {});
+        }
+        validate_denom(deps.api, &funding.denom)?;
+        ALLOW_LIST.save(deps.storage, &funding.denom, &Empty {})?;
+
+        let held = balance_of(deps.as_ref(), env, &funding.denom)?;
+        let cfg = Config {
+            admin: old.admin.clone(),
+            title: old.title,
+            target: funding.target,
+            denom: funding.denom,
+            deadline: funding.deadline,
+            guardian: None,
+        };
+        CONFIG.save(deps.storage, &cfg)?;
+        if let Some(factory) = funding.factory {
+            FACTORY.save(deps.storage, &deps.api.addr_validate(&factory)?)?;
+        }
+        RAISED.save(deps.storage, &held)?;
+        let status = match held >= cfg.target {
+            true => PoolStatus::Succeeded,
+            false => PoolStatus::Open,
+        };
+        STATUS.save(deps.storage, &status)?;
+        BENEFICIARIES.save(deps.storage, &vec![])?;
+        TOTAL.save(deps.storage, &0, env.block.height)?;
+        if !held.is_zero() {
+            CONTRIBUTIONS.save(deps.storage, (&old.admin, &cfg.denom), &held)?;
+            // a legacy pool has no hooks registered to notify
+            update_membership(deps.storage, &old.admin, env.block.height)?;
+        }
+        Ok(())
+    }
+}
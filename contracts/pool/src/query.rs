@@ -1,20 +1,225 @@
 
-use cosmwasm_std::{Deps, StdResult};
+use cosmwasm_std::{Addr, Coin, Deps, Env, Order, StdResult};
+use cw4::{AdminResponse, Member, MemberListResponse, MemberResponse, TotalWeightResponse};
+use cw_storage_plus::{Bound, PrefixBound};
+use cw_utils::maybe_addr;
 
-use crate::{state::CONFIG, msg::ConfigResponse};
+use crate::{
+    msg::{
+        BeneficiariesResponse, Beneficiary, ConfigResponse, ContributionResponse,
+        ListAllowedDenomsResponse, ListContributionsResponse, MilestoneResponse,
+        MilestonesResponse, StatusResponse, StreamResponse, VestingStatusResponse,
+        WithdrawnResponse,
+    },
+    state::{
+        load_admin, load_guardian, load_status, ALLOW_LIST, BENEFICIARIES, CONFIG, CONTRIBUTIONS, MEMBERS, MILESTONES,
+        MILESTONE_THRESHOLD, PAUSED, RAISED, SHARE_TOKEN, FACTORY, STREAMS, TOTAL, WITHDRAWN,
+    },
+};
 
 
 
 // settings for pagination
-// const MAX_LIMIT: u32 = 30;
-// const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
 
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let cfg = CONFIG.load(deps.storage)?;
     
     let res = ConfigResponse {
-        admin: cfg.admin.to_string(),   
+        admin: load_admin(deps)?.to_string(),
         title: cfg.title,
+        target: cfg.target,
+        denom: cfg.denom,
+        deadline: cfg.deadline,
+        raised: RAISED.load(deps.storage)?,
+        guardian: load_guardian(deps)?.map(String::from),
+        paused: PAUSED.may_load(deps.storage)?.unwrap_or_default(),
+        share_token: SHARE_TOKEN.may_load(deps.storage)?.map(String::from),
+        factory: FACTORY.may_load(deps.storage)?.map(String::from),
     };
     Ok(res)
-}
\ No newline at end of file
+}
+
+pub fn query_status(deps: Deps, env: Env) -> StdResult<StatusResponse> {
+    Ok(StatusResponse {
+        status: load_status(deps.storage, &env.block)?,
+    })
+}
+
+pub fn query_contribution(deps: Deps, address: String) -> StdResult<ContributionResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let contributions = CONTRIBUTIONS
+        .prefix(&addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect::<StdResult<_>>()?;
+    Ok(ContributionResponse {
+        address,
+        contributions,
+    })
+}
+
+pub fn query_list_contributions(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListContributionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let min = start.as_ref().map(PrefixBound::exclusive);
+
+    // entries are sorted by (contributor, denom), so group consecutive denoms per contributor
+    let mut contributions: Vec<ContributionResponse> = vec![];
+    for item in CONTRIBUTIONS.prefix_range(deps.storage, min, None, Order::Ascending) {
+        let ((addr, denom), amount): ((Addr, String), _) = item?;
+        let coin = Coin { denom, amount };
+        if let Some(last) = contributions.last_mut() {
+            if last.address == addr.as_str() {
+                last.contributions.push(coin);
+                continue;
+            }
+        }
+        if contributions.len() == limit {
+            break;
+        }
+        contributions.push(ContributionResponse {
+            address: addr.into(),
+            contributions: vec![coin],
+        });
+    }
+    Ok(ListContributionsResponse { contributions })
+}
+
+pub fn query_list_allowed_denoms(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListAllowedDenomsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let denoms = ALLOW_LIST
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+    Ok(ListAllowedDenomsResponse { denoms })
+}
+
+pub fn query_withdrawn(deps: Deps) -> StdResult<WithdrawnResponse> {
+    let withdrawn = WITHDRAWN
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect::<StdResult<_>>()?;
+    Ok(WithdrawnResponse { withdrawn })
+}
+
+pub fn query_beneficiaries(deps: Deps) -> StdResult<BeneficiariesResponse> {
+    let beneficiaries = BENEFICIARIES
+        .load(deps.storage)?
+        .into_iter()
+        .map(|beneficiary| Beneficiary {
+            address: beneficiary.address.into(),
+            share: beneficiary.share,
+        })
+        .collect();
+    Ok(BeneficiariesResponse { beneficiaries })
+}
+
+pub fn query_milestones(deps: Deps, env: Env) -> StdResult<MilestonesResponse> {
+    let threshold = MILESTONE_THRESHOLD.may_load(deps.storage)?;
+    let total = RAISED.load(deps.storage)?;
+
+    let mut milestones = vec![];
+    if let Some(threshold) = threshold.as_ref() {
+        for item in MILESTONES.range(deps.storage, None, None, Order::Ascending) {
+            let (id, milestone) = item?;
+            milestones.push(MilestoneResponse {
+                id,
+                status: milestone.current_status(&env.block, threshold, total),
+                description: milestone.description,
+                amount: milestone.amount,
+                deadline: milestone.deadline,
+                yes: milestone.yes,
+                no: milestone.no,
+            });
+        }
+    }
+    Ok(MilestonesResponse {
+        threshold,
+        milestones,
+    })
+}
+
+pub fn query_admin(deps: Deps) -> StdResult<AdminResponse> {
+    Ok(AdminResponse {
+        admin: Some(load_admin(deps)?.into()),
+    })
+}
+
+pub fn query_total_weight(deps: Deps, height: Option<u64>) -> StdResult<TotalWeightResponse> {
+    let weight = match height {
+        Some(h) => TOTAL.may_load_at_height(deps.storage, h),
+        None => TOTAL.may_load(deps.storage),
+    }?
+    .unwrap_or_default();
+    Ok(TotalWeightResponse { weight })
+}
+
+pub fn query_member(deps: Deps, addr: String, height: Option<u64>) -> StdResult<MemberResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let weight = match height {
+        Some(h) => MEMBERS.may_load_at_height(deps.storage, &addr, h),
+        None => MEMBERS.may_load(deps.storage, &addr),
+    }?;
+    Ok(MemberResponse { weight })
+}
+
+pub fn query_list_members(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MemberListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.as_ref().map(Bound::exclusive);
+
+    let members = MEMBERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(addr, weight)| Member {
+                addr: addr.into(),
+                weight,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(MemberListResponse { members })
+}
+
+pub fn query_vesting_status(
+    deps: Deps,
+    env: Env,
+    recipient: String,
+) -> StdResult<VestingStatusResponse> {
+    let addr = deps.api.addr_validate(&recipient)?;
+    let streams = STREAMS
+        .prefix(&addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(id, stream)| StreamResponse {
+                id,
+                vested: stream.vested(&env.block),
+                denom: stream.denom,
+                amount: stream.amount,
+                claimed: stream.claimed,
+                start: stream.start,
+                end: stream.end,
+                cliff: stream.cliff,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(VestingStatusResponse { streams })
+}
@@ -0,0 +1,25 @@
+use cosmwasm_std::{DepsMut, Reply, Response, StdError, StdResult};
+use cw_utils::parse_reply_instantiate_data;
+
+use crate::state::SHARE_TOKEN;
+
+/// Records the address of the share token instantiated along with the pool
+pub fn handle_share_token_reply(deps: DepsMut, msg: Reply) -> StdResult<Response> {
+    let res = parse_reply_instantiate_data(msg)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let token = deps.api.addr_validate(&res.contract_address)?;
+    SHARE_TOKEN.save(deps.storage, &token)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate_share_token")
+        .add_attribute("share_token", token))
+}
+
+/// A failing contribution hook must not revert the contribution, refund or withdrawal it reports,
+/// so its error is only recorded. Hooks run with `CONTRIBUTION_HOOK_GAS_LIMIT`, which makes
+/// running out of gas one of these errors too.
+pub fn handle_contribution_hook_reply(msg: Reply) -> Response {
+    Response::new()
+        .add_attribute("action", "contribution_hook_failed")
+        .add_attribute("error", msg.result.unwrap_err())
+}
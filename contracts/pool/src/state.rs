@@ -1,15 +1,289 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, };
-use cw_storage_plus::{Item, };
+use cosmwasm_std::{
+    Addr, BlockInfo, Decimal, Deps, Empty, Order, StdResult, Storage, Uint128, Uint256,
+};
+use cw_controllers::Hooks;
+use cw_storage_plus::{Item, Map, SnapshotItem, SnapshotMap, Strategy};
+use cw_utils::{Expiration, Threshold};
 
+use pool_types::factory::{ConfigResponse as FactoryConfigResponse, QueryMsg as FactoryQueryMsg};
+
+pub use pool_types::pool::{MilestoneStatus, PoolStatus};
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// last persisted lifecycle state, use `load_status` to take the deadline into account
+pub const STATUS: Item<PoolStatus> = Item::new("status");
+
+/// set by the guardian to block contributions, refunds keep working
+pub const PAUSED: Item<bool> = Item::new("paused");
+
+/// total amount of the target denom contributed so far
+pub const RAISED: Item<Uint128> = Item::new("raised");
+
+/// Every denom (native or `cw20:<contract>`) we accept as contribution, the target denom included
+pub const ALLOW_LIST: Map<&str, Empty> = Map::new("allow_list");
+
+/// who withdrawals are split between, empty if they go to a single recipient
+pub const BENEFICIARIES: Item<Vec<BeneficiaryVerified>> = Item::new("beneficiaries");
+
+/// tranches released one by one once contributors approve them, empty if the admin can withdraw freely
+pub const MILESTONES: Map<u64, Milestone> = Map::new("milestones");
+
+/// how milestone votes are tallied, only set if the pool has milestones
+pub const MILESTONE_THRESHOLD: Item<Threshold> = Item::new("milestone_threshold");
+
+/// indexed by (milestone id, voter), whether they approved
+pub const BALLOTS: Map<(u64, &Addr), bool> = Map::new("ballots");
+
+/// total withdrawn by the admin so far, per denom
+pub const WITHDRAWN: Map<&str, Uint128> = Map::new("withdrawn");
+
+/// indexed by (recipient, stream id), payouts released linearly instead of sent right away
+pub const STREAMS: Map<(&Addr, u64), Stream> = Map::new("streams");
+pub const STREAM_COUNT: Item<u64> = Item::new("stream_count");
+
+/// per denom, what the streams still hold back. Withdrawals can only take the rest of the balance.
+pub const VESTING: Map<&str, Uint128> = Map::new("vesting");
+
+/// cw4 view of the contributors, weighted by what they contributed in the target denom
+pub const MEMBERS: SnapshotMap<&Addr, u64> = SnapshotMap::new(
+    cw4::MEMBERS_KEY,
+    cw4::MEMBERS_CHECKPOINTS,
+    cw4::MEMBERS_CHANGELOG,
+    Strategy::EveryBlock,
+);
+
+/// target denom contributed per unit of member weight, 1 if not set
+pub const TOKENS_PER_WEIGHT: Item<Uint128> = Item::new("tokens_per_weight");
+
+pub const TOTAL: SnapshotItem<u64> = SnapshotItem::new(
+    cw4::TOTAL_KEY,
+    cw4::TOTAL_KEY_CHECKPOINTS,
+    cw4::TOTAL_KEY_CHANGELOG,
+    Strategy::EveryBlock,
+);
+
+/// cw20 contract the pool mints its shares on, only set if it was created with one
+pub const SHARE_TOKEN: Item<Addr> = Item::new("share_token");
+
+/// factory the pool was created by, the only one allowed to contribute on behalf of others
+pub const FACTORY: Item<Addr> = Item::new("factory");
+
+/// notified with a `MemberChangedHookMsg` whenever a member weight changes
+pub const HOOKS: Hooks = Hooks::new("cw4-hooks");
+
+/// notified with a `ContributionHookMsg` whenever funds enter or leave the pool
+pub const CONTRIBUTION_HOOKS: Hooks = Hooks::new("contribution_hooks");
+
+/// indexed by (contributor, denom) maintaining the total contributed in that currency
+pub const CONTRIBUTIONS: Map<(&Addr, &str), Uint128> = Map::new("contributions");
+
 #[cw_serde]
 pub struct Config {
+    /// only used if the pool has no factory, see `load_admin`
     pub admin: Addr,
     pub title: String,
+    pub target: Uint128,
+    pub denom: String,
+    pub deadline: Expiration,
+    /// can pause contributions, only the admin can resume them. Only used if the pool has no
+    /// factory, see `load_guardian`
+    pub guardian: Option<Addr>,
+}
+
+fn factory_config(deps: Deps, factory: &Addr) -> StdResult<FactoryConfigResponse> {
+    deps.querier
+        .query_wasm_smart(factory, &FactoryQueryMsg::Config {})
+}
+
+/// Pools created by a factory answer to its current admin, so transferring the factory hands
+/// over its pools as well
+pub fn load_admin(deps: Deps) -> StdResult<Addr> {
+    match FACTORY.may_load(deps.storage)? {
+        Some(factory) => {
+            let cfg = factory_config(deps, &factory)?;
+            deps.api.addr_validate(&cfg.admin)
+        }
+        None => Ok(CONFIG.load(deps.storage)?.admin),
+    }
 }
 
+/// Like `load_admin`, the factory guardian if the pool has a factory
+pub fn load_guardian(deps: Deps) -> StdResult<Option<Addr>> {
+    match FACTORY.may_load(deps.storage)? {
+        Some(factory) => factory_config(deps, &factory)?
+            .guardian
+            .map(|guardian| deps.api.addr_validate(&guardian))
+            .transpose(),
+        None => Ok(CONFIG.load(deps.storage)?.guardian),
+    }
+}
+
+/// Full share, in basis points
+pub const TOTAL_SHARES: u16 = 10_000;
+
+#[cw_serde]
+pub struct BeneficiaryVerified {
+    pub address: Addr,
+    /// in basis points
+    pub share: u16,
+}
+
+#[cw_serde]
+pub struct Milestone {
+    pub description: String,
+    /// tranche of the target denom released once approved
+    pub amount: Uint128,
+    /// voting closes at this point, the milestone is rejected if it did not pass by then
+    pub deadline: Expiration,
+    pub released: bool,
+    /// contribution weight that approved
+    pub yes: Uint128,
+    /// contribution weight that disapproved
+    pub no: Uint128,
+}
+
+#[cw_serde]
+pub struct Stream {
+    /// native or `cw20:<contract>`
+    pub denom: String,
+    pub amount: Uint128,
+    pub claimed: Uint128,
+    pub start: Expiration,
+    pub end: Expiration,
+    pub cliff: Option<Expiration>,
+}
+
+impl Stream {
+    /// Linear share of `amount` between `start` and `end`, nothing before the cliff
+    pub fn vested(&self, block: &BlockInfo) -> Uint128 {
+        if let Some(cliff) = self.cliff {
+            if !cliff.is_expired(block) {
+                return Uint128::zero();
+            }
+        }
+        match (progress(&self.start, block), progress(&self.end, block)) {
+            (Some((start, now)), Some((end, _))) if now < end => match now > start {
+                true => self.amount.multiply_ratio(now - start, end - start),
+                false => Uint128::zero(),
+            },
+            _ => self.amount,
+        }
+    }
 
+    /// Stops the stream at `block`, returning the unvested part. What vested so far becomes
+    /// claimable right away, so it is never less than what was claimed already.
+    pub fn cancel(&mut self, block: &BlockInfo) -> Uint128 {
+        let vested = self.vested(block);
+        let unvested = self.amount - vested;
+        self.amount = vested;
+        self.end = match self.start {
+            Expiration::AtTime(_) => Expiration::AtTime(block.time),
+            _ => Expiration::AtHeight(block.height),
+        };
+        unvested
+    }
+}
+
+/// The point an expiration refers to along with where the block is, in the same unit
+pub fn progress(expiration: &Expiration, block: &BlockInfo) -> Option<(u64, u64)> {
+    match expiration {
+        Expiration::AtHeight(height) => Some((*height, block.height)),
+        Expiration::AtTime(time) => Some((time.nanos(), block.time.nanos())),
+        Expiration::Never {} => None,
+    }
+}
+
+/// Increments the stream counter and returns the new value
+pub fn next_stream_id(store: &mut dyn Storage) -> StdResult<u64> {
+    let id = STREAM_COUNT.may_load(store)?.unwrap_or_default() + 1;
+    STREAM_COUNT.save(store, &id)?;
+    Ok(id)
+}
+
+impl Milestone {
+    /// Current status given the votes so far, `total` being the weight of all contributors
+    pub fn current_status(
+        &self,
+        block: &BlockInfo,
+        threshold: &Threshold,
+        total: Uint128,
+    ) -> MilestoneStatus {
+        if self.released {
+            MilestoneStatus::Released
+        } else if self.is_passed(block, threshold, total) {
+            MilestoneStatus::Passed
+        } else if self.deadline.is_expired(block) || self.is_rejected(threshold, total) {
+            MilestoneStatus::Rejected
+        } else {
+            MilestoneStatus::Voting
+        }
+    }
 
+    /// Same rules as cw3 proposals: before the deadline a quorum threshold has to be met
+    /// against the total weight, after it against the votes cast.
+    fn is_passed(&self, block: &BlockInfo, threshold: &Threshold, total: Uint128) -> bool {
+        match threshold {
+            Threshold::AbsoluteCount { weight } => self.yes >= Uint128::from(*weight),
+            Threshold::AbsolutePercentage { percentage } => {
+                self.yes >= votes_needed(total, *percentage)
+            }
+            Threshold::ThresholdQuorum { threshold, quorum } => {
+                let votes = self.yes + self.no;
+                if votes < votes_needed(total, *quorum) {
+                    return false;
+                }
+                match self.deadline.is_expired(block) {
+                    true => self.yes >= votes_needed(votes, *threshold),
+                    false => self.yes >= votes_needed(total, *threshold),
+                }
+            }
+        }
+    }
+
+    /// True once the votes against make passing impossible, even if everyone left approves
+    fn is_rejected(&self, threshold: &Threshold, total: Uint128) -> bool {
+        let possible = total - self.no;
+        match threshold {
+            Threshold::AbsoluteCount { weight } => possible < Uint128::from(*weight),
+            Threshold::AbsolutePercentage { percentage } => {
+                possible < votes_needed(total, *percentage)
+            }
+            Threshold::ThresholdQuorum { threshold, .. } => {
+                possible < votes_needed(total, *threshold)
+            }
+        }
+    }
+}
+
+/// `weight * percentage`, rounded up
+fn votes_needed(weight: Uint128, percentage: Decimal) -> Uint128 {
+    let fractional = Uint256::from(Decimal::one().atomics());
+    let applied = Uint256::from(weight) * Uint256::from(percentage.atomics());
+    let needed = (applied + fractional - Uint256::one()) / fractional;
+    // never more than `weight` for a valid percentage
+    needed.try_into().unwrap_or(Uint128::MAX)
+}
+
+/// Returns the current status, an open pool whose deadline passed being reported as failed.
+/// Reaching the target moves the pool out of `Open` right away, so it cannot have succeeded.
+/// Likewise a succeeded pool fails as soon as one of its milestones is rejected.
+pub fn load_status(storage: &dyn Storage, block: &BlockInfo) -> StdResult<PoolStatus> {
+    let status = STATUS.load(storage)?;
+    if status == PoolStatus::Open && CONFIG.load(storage)?.deadline.is_expired(block) {
+        return Ok(PoolStatus::Failed);
+    }
+    if status == PoolStatus::Succeeded {
+        if let Some(threshold) = MILESTONE_THRESHOLD.may_load(storage)? {
+            let total = RAISED.load(storage)?;
+            for item in MILESTONES.range(storage, None, None, Order::Ascending) {
+                let (_, milestone) = item?;
+                if milestone.current_status(block, &threshold, total) == MilestoneStatus::Rejected {
+                    return Ok(PoolStatus::Failed);
+                }
+            }
+        }
+    }
+    Ok(status)
+}
@@ -1,201 +1,1593 @@
-#[cfg(test)]
-use super::*;
-use crate::test_helpers::*;
-
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-use cosmwasm_std::{coin, coins, CosmosMsg, IbcMsg, StdError, Uint128};
-
-use crate::state::ChannelState;
-use cw_utils::PaymentError;
-
-#[test]
-fn setup_and_query() {   
-    assert_eq!(err, StdError::not_found("cw20_ics20::state::ChannelInfo"));
-}
-
-#[test]
-fn proper_checks_on_execute_native() {
-    let send_channel = "channel-5";
-    let mut deps = setup(&[send_channel, "channel-10"], &[]);
-
-    let mut transfer = TransferMsg {
-        channel: send_channel.to_string(),
-        remote_address: "foreign-address".to_string(),
-        timeout: None,
-    };
-
-    // works with proper funds
-    let msg = ExecuteMsg::Transfer(transfer.clone());
-    let info = mock_info("foobar", &coins(1234567, "ucosm"));
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(res.messages[0].gas_limit, None);
-    assert_eq!(1, res.messages.len());
-    if let CosmosMsg::Ibc(IbcMsg::SendPacket {
-        channel_id,
-        data,
-        timeout,
-    }) = &res.messages[0].msg
-    {
-        let expected_timeout = mock_env().block.time.plus_seconds(DEFAULT_TIMEOUT);
-        assert_eq!(timeout, &expected_timeout.into());
-        assert_eq!(channel_id.as_str(), send_channel);
-        let msg: Ics20Packet = from_binary(data).unwrap();
-        assert_eq!(msg.amount, Uint128::new(1234567));
-        assert_eq!(msg.denom.as_str(), "ucosm");
-        assert_eq!(msg.sender.as_str(), "foobar");
-        assert_eq!(msg.receiver.as_str(), "foreign-address");
-    } else {
-        panic!("Unexpected return message: {:?}", res.messages[0]);
+use crate::contract::{
+    execute, instantiate, migrate, query, reply, CONTRIBUTION_HOOK_GAS_LIMIT,
+    CONTRIBUTION_HOOK_REPLY_ID, SHARE_TOKEN_REPLY_ID,
+};
+use crate::migrations::{v1, LEGACY_CONTRACT_NAME};
+use crate::msg::{
+    Beneficiary, ContributionChange, ContributionHookMsg, Cw20InstantiateMsg, Funding, MigrateMsg,
+    MilestoneMsg, MilestonesResponse, ReceiveMsg, ShareTokenMsg,
+};
+use crate::msg::{
+    ConfigResponse, ContributionResponse, ExecuteMsg, InitMsg, ListAllowedDenomsResponse,
+    ListContributionsResponse, QueryMsg, StatusResponse, VestingMsg, VestingStatusResponse,
+    WithdrawnResponse,
+};
+use crate::state::{MilestoneStatus, PoolStatus};
+use crate::ContractError;
+
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    attr, coin, coins, from_binary, to_binary, Addr, BankMsg, Binary, ContractResult, CosmosMsg,
+    Decimal, Deps, DepsMut, Env, Reply, SubMsg, SubMsgResponse, SubMsgResult, SystemResult,
+    Uint128, WasmMsg, WasmQuery,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
+use cw4::{
+    Member, MemberChangedHookMsg, MemberDiff, MemberListResponse, MemberResponse,
+    TotalWeightResponse,
+};
+use cw_controllers::HooksResponse;
+use cw_utils::{Expiration, PaymentError, Threshold};
+use pool_types::factory::{ConfigResponse as FactoryConfigResponse, CreationPolicy};
+
+const ADMIN: &str = "admin";
+const DENOM: &str = "uatom";
+const TARGET: u128 = 1000;
+
+fn init_msg() -> InitMsg {
+    InitMsg {
+        admin: ADMIN.to_string(),
+        title: "pool".to_string(),
+        target: Uint128::new(TARGET),
+        denom: DENOM.to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        guardian: None,
+        share_token: None,
+        factory: None,
+        tokens_per_weight: None,
     }
+}
+
+fn setup(deps: DepsMut) {
+    let info = mock_info("creator", &[]);
+    instantiate(deps, mock_env(), info, init_msg()).unwrap();
+}
+
+fn contribute() -> ExecuteMsg {
+    ExecuteMsg::Contribute { on_behalf_of: None }
+}
+
+fn after_deadline() -> Env {
+    let mut env = mock_env();
+    env.block.height += 100;
+    env
+}
+
+fn status(deps: Deps, env: Env) -> PoolStatus {
+    let res: StatusResponse = from_binary(&query(deps, env, QueryMsg::Status {}).unwrap()).unwrap();
+    res.status
+}
+
+#[test]
+fn proper_init() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+
+    let cfg = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_binary(&cfg).unwrap();
+    assert_eq!(ADMIN, value.admin);
+    assert_eq!("pool", value.title);
+}
+
+#[test]
+fn contributions_are_recorded() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
 
     // reject with no funds
-    let msg = ExecuteMsg::Transfer(transfer.clone());
-    let info = mock_info("foobar", &[]);
-    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    let info = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap_err();
     assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
 
-    // reject with multiple tokens funds
-    let msg = ExecuteMsg::Transfer(transfer.clone());
-    let info = mock_info("foobar", &[coin(1234567, "ucosm"), coin(54321, "uatom")]);
-    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    // reject any other denom
+    let info = mock_info("alice", &[coin(100, "uatom"), coin(5, "ujuno")]);
+    let err = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap_err();
     assert_eq!(err, ContractError::Payment(PaymentError::MultipleDenoms {}));
 
-    // reject with bad channel id
-    transfer.channel = "channel-45".to_string();
-    let msg = ExecuteMsg::Transfer(transfer);
-    let info = mock_info("foobar", &coins(1234567, "ucosm"));
-    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    let info = mock_info("alice", &coins(100, "uatom"));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    let info = mock_info("alice", &coins(50, "uatom"));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+
+    let msg = QueryMsg::Contribution {
+        address: "alice".to_string(),
+    };
+    let res: ContributionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.contributions, vec![coin(150, "uatom")]);
+
+    // unknown contributors have an empty record
+    let msg = QueryMsg::Contribution {
+        address: "bob".to_string(),
+    };
+    let res: ContributionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(res.contributions.is_empty());
+}
+
+#[test]
+fn factory_contributes_on_behalf_of_others() {
+    let mut deps = mock_dependencies();
+    let msg = InitMsg {
+        factory: Some("factory".to_string()),
+        ..init_msg()
+    };
+    // only the factory itself can register as such
+    let info = mock_info("creator", &[]);
+    let err = instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    instantiate(deps.as_mut(), mock_env(), mock_info("factory", &[]), msg).unwrap();
+
+    let on_behalf = ExecuteMsg::Contribute {
+        on_behalf_of: Some("bob".to_string()),
+    };
+    let info = mock_info("alice", &coins(100, DENOM));
+    let err = execute(deps.as_mut(), mock_env(), info, on_behalf.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let info = mock_info("factory", &coins(100, DENOM));
+    let res = execute(deps.as_mut(), mock_env(), info, on_behalf).unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "contributor" && attr.value == "bob"));
+
+    let msg = QueryMsg::Contribution {
+        address: "bob".to_string(),
+    };
+    let res: ContributionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.contributions, coins(100, DENOM));
+    let msg = QueryMsg::Contribution {
+        address: "factory".to_string(),
+    };
+    let res: ContributionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(res.contributions.is_empty());
+
+    // the pool answers to the factory admin and guardian, not to the ones it was created with
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == "factory" => {
+            let cfg = FactoryConfigResponse {
+                admin: "factory_admin".to_string(),
+                pool_code_id: 1,
+                receipt_nft: None,
+                creation_policy: CreationPolicy::Permissionless,
+                guardian: Some("factory_guardian".to_string()),
+                paused: false,
+            };
+            SystemResult::Ok(ContractResult::Ok(to_binary(&cfg).unwrap()))
+        }
+        _ => panic!("unexpected query"),
+    });
+    let cfg: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.factory, Some("factory".to_string()));
+    assert_eq!(cfg.admin, "factory_admin");
+    assert_eq!(cfg.guardian, Some("factory_guardian".to_string()));
+    assert_eq!(cfg.raised, Uint128::new(100));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let info = mock_info("factory_guardian", &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+    let info = mock_info("factory_admin", &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Cancel {}).unwrap();
+}
+
+#[test]
+fn list_contributions_paginates_by_contributor() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+
+    for addr in ["carol", "alice", "bob"] {
+        let info = mock_info(addr, &coins(10, "uatom"));
+        execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    }
+
+    let msg = QueryMsg::ListContributions {
+        start_after: None,
+        limit: Some(2),
+    };
+    let res: ListContributionsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let addrs: Vec<_> = res
+        .contributions
+        .iter()
+        .map(|c| c.address.as_str())
+        .collect();
+    assert_eq!(addrs.len(), 2);
+    assert_eq!(res.contributions[1].contributions, vec![coin(10, "uatom")]);
+
+    let msg = QueryMsg::ListContributions {
+        start_after: Some(addrs[1].to_string()),
+        limit: Some(2),
+    };
+    let res: ListContributionsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let mut all: Vec<_> = res
+        .contributions
+        .iter()
+        .map(|c| c.address.as_str())
+        .chain(addrs)
+        .collect();
+    all.sort();
+    assert_eq!(all, vec!["alice", "bob", "carol"]);
+}
+
+#[test]
+fn withdraw_requires_target() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+
+    let info = mock_info("alice", &coins(TARGET - 1, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: None,
+        vesting: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidStatus {
+            expected: PoolStatus::Succeeded,
+            status: PoolStatus::Open
+        }
+    );
+
+    let info = mock_info("bob", &coins(1, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(TARGET, DENOM));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+
+    // target reached, nobody can get a refund
+    let err = execute(
+        deps.as_mut(),
+        after_deadline(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidStatus {
+            expected: PoolStatus::Failed,
+            status: PoolStatus::Succeeded
+        }
+    );
+}
+
+#[test]
+fn refund_after_missed_deadline() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+
+    let info = mock_info("alice", &coins(300, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+
+    // not before the deadline
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidStatus {
+            expected: PoolStatus::Failed,
+            status: PoolStatus::Open
+        }
+    );
+
+    // no more contributions after it
+    let info = mock_info("bob", &coins(TARGET, DENOM));
+    let err = execute(deps.as_mut(), after_deadline(), info, contribute()).unwrap_err();
     assert_eq!(
         err,
-        ContractError::NoSuchChannel {
-            id: "channel-45".to_string()
+        ContractError::InvalidStatus {
+            expected: PoolStatus::Open,
+            status: PoolStatus::Failed
         }
     );
+
+    let res = execute(
+        deps.as_mut(),
+        after_deadline(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: coins(300, DENOM),
+        })
+    );
+
+    // only once
+    let err = execute(
+        deps.as_mut(),
+        after_deadline(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NothingToRefund {});
 }
 
 #[test]
-fn proper_checks_on_execute_cw20() {
-    let send_channel = "channel-15";
-    let cw20_addr = "my-token";
-    let mut deps = setup(&["channel-3", send_channel], &[(cw20_addr, 123456)]);
+fn status_transitions() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Open);
+    // the deadline alone fails an open pool
+    assert_eq!(status(deps.as_ref(), after_deadline()), PoolStatus::Failed);
 
-    let transfer = TransferMsg {
-        channel: send_channel.to_string(),
-        remote_address: "foreign-address".to_string(),
-        timeout: Some(7777),
+    // only the admin can cancel
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap();
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Failed);
+
+    // reaching the target succeeds right away, then the admin can close
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    let info = mock_info("alice", &coins(TARGET, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    assert_eq!(
+        status(deps.as_ref(), after_deadline()),
+        PoolStatus::Succeeded
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidStatus {
+            expected: PoolStatus::Open,
+            status: PoolStatus::Succeeded
+        }
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Close {},
+    )
+    .unwrap();
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Closed);
+}
+
+#[test]
+fn cw20_contributions() {
+    let mut deps = mock_dependencies();
+    let msg = InitMsg {
+        denom: "cw20:token".to_string(),
+        ..init_msg()
     };
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "my-account".into(),
-        amount: Uint128::new(888777666),
-        msg: to_binary(&transfer).unwrap(),
-    });
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-    // works with proper funds
-    let info = mock_info(cw20_addr, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-    assert_eq!(1, res.messages.len());
-    assert_eq!(res.messages[0].gas_limit, None);
-    if let CosmosMsg::Ibc(IbcMsg::SendPacket {
-        channel_id,
-        data,
-        timeout,
-    }) = &res.messages[0].msg
-    {
-        let expected_timeout = mock_env().block.time.plus_seconds(7777);
-        assert_eq!(timeout, &expected_timeout.into());
-        assert_eq!(channel_id.as_str(), send_channel);
-        let msg: Ics20Packet = from_binary(data).unwrap();
-        assert_eq!(msg.amount, Uint128::new(888777666));
-        assert_eq!(msg.denom, format!("cw20:{}", cw20_addr));
-        assert_eq!(msg.sender.as_str(), "my-account");
-        assert_eq!(msg.receiver.as_str(), "foreign-address");
-    } else {
-        panic!("Unexpected return message: {:?}", res.messages[0]);
-    }
+    let receive = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "alice".to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&ReceiveMsg::Contribute { on_behalf_of: None }).unwrap(),
+        })
+    };
+
+    // only the configured token
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("other", &[]),
+        receive(10),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidDenom {
+            denom: "cw20:other".to_string()
+        }
+    );
+    // and no native coins
+    let info = mock_info("alice", &coins(10, DENOM));
+    let err = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidDenom {
+            denom: DENOM.to_string()
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("token", &[]),
+        receive(300),
+    )
+    .unwrap();
+    let msg = QueryMsg::Contribution {
+        address: "alice".to_string(),
+    };
+    let res: ContributionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.contributions, vec![coin(300, "cw20:token")]);
 
-    // reject with tokens funds
-    let info = mock_info("foobar", &coins(1234567, "ucosm"));
-    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(err, ContractError::Payment(PaymentError::NonPayable {}));
+    // refunds go back through the token contract
+    let res = execute(
+        deps.as_mut(),
+        after_deadline(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "alice".to_string(),
+                amount: Uint128::new(300),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
 }
 
 #[test]
-fn execute_cw20_fails_if_not_whitelisted_unless_default_gas_limit() {
-    let send_channel = "channel-15";
-    let mut deps = setup(&[send_channel], &[]);
+fn allowed_denoms() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
 
-    let cw20_addr = "my-token";
-    let transfer = TransferMsg {
-        channel: send_channel.to_string(),
-        remote_address: "foreign-address".to_string(),
-        timeout: Some(7777),
+    let allowed = |deps: Deps| -> Vec<String> {
+        let msg = QueryMsg::ListAllowedDenoms {
+            start_after: None,
+            limit: None,
+        };
+        let res: ListAllowedDenomsResponse =
+            from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        res.denoms
     };
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "my-account".into(),
-        amount: Uint128::new(888777666),
-        msg: to_binary(&transfer).unwrap(),
-    });
+    assert_eq!(allowed(deps.as_ref()), vec![DENOM.to_string()]);
 
-    // rejected as not on allow list
-    let info = mock_info(cw20_addr, &[]);
-    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
-    assert_eq!(err, ContractError::NotOnAllowList);
+    // not allowed yet
+    let info = mock_info("alice", &coins(10, "ujuno"));
+    let err = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidDenom {
+            denom: "ujuno".to_string()
+        }
+    );
 
-    // add a default gas limit
-    migrate(
+    // only the admin manages the list
+    let msg = ExecuteMsg::AddAllowedDenom {
+        denom: "ujuno".to_string(),
+    };
+    let err = execute(
         deps.as_mut(),
         mock_env(),
-        MigrateMsg {
-            default_gas_limit: Some(123456),
-        },
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        allowed(deps.as_ref()),
+        vec![DENOM.to_string(), "ujuno".to_string()]
+    );
+
+    // accepted, but it does not count towards the target
+    let info = mock_info("alice", &coins(TARGET, "ujuno"));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    let msg = QueryMsg::Contribution {
+        address: "alice".to_string(),
+    };
+    let res: ContributionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.contributions, vec![coin(TARGET, "ujuno")]);
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Open);
+
+    // the target denom stays
+    let msg = ExecuteMsg::RemoveAllowedDenom {
+        denom: DENOM.to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::CannotRemoveTargetDenom {});
+
+    let msg = ExecuteMsg::RemoveAllowedDenom {
+        denom: "ujuno".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(allowed(deps.as_ref()), vec![DENOM.to_string()]);
+}
+
+#[test]
+fn partial_withdrawals() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    let info = mock_info("alice", &coins(TARGET, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    deps.querier.update_balance(
+        mock_env().contract.address,
+        vec![coin(TARGET, DENOM), coin(50, "ujuno")],
+    );
+
+    let withdraw = |amount: Option<Vec<_>>| ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount,
+        vesting: None,
+    };
+
+    // not more than we hold
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        withdraw(Some(coins(TARGET + 1, DENOM))),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientBalance {
+            denom: DENOM.to_string(),
+            available: Uint128::new(TARGET),
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        withdraw(Some(coins(400, DENOM))),
     )
     .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(400, DENOM),
+        })
+    );
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "amount" && attr.value == "400uatom"));
 
-    // try again
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // the rest of everything
+    deps.querier.update_balance(
+        mock_env().contract.address,
+        vec![coin(TARGET - 400, DENOM), coin(50, "ujuno")],
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        withdraw(None),
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "amount" && attr.value == "600uatom,50ujuno"));
+
+    let res: WithdrawnResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawn {}).unwrap()).unwrap();
+    assert_eq!(res.withdrawn, vec![coin(TARGET, DENOM), coin(50, "ujuno")]);
 }
 
 #[test]
-fn v3_migration_works() {
-    // basic state with one channel
-    let send_channel = "channel-15";
-    let cw20_addr = "my-token";
-    let native = "ucosm";
-    let mut deps = setup(&[send_channel], &[(cw20_addr, 123456)]);
+fn beneficiary_splits() {
+    let mut deps = mock_dependencies();
+    let beneficiary = |address: &str, share: u16| Beneficiary {
+        address: address.to_string(),
+        share,
+    };
+    let init = |beneficiaries: Vec<Beneficiary>| InitMsg {
+        allowed_denoms: vec!["cw20:token".to_string()],
+        beneficiaries,
+        ..init_msg()
+    };
 
-    // mock that we sent some tokens in both native and cw20 (TODO: cw20)
-    // balances set high
+    // shares must add up to the whole
+    let msg = init(vec![beneficiary("ann", 5000), beneficiary("ben", 4000)]);
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidShares { total: 9000 });
+    let msg = init(vec![beneficiary("ann", 5000), beneficiary("ann", 5000)]);
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::DuplicateBeneficiary {
+            address: "ann".to_string()
+        }
+    );
+
+    let msg = init(vec![
+        beneficiary("ann", 3334),
+        beneficiary("ben", 3333),
+        beneficiary("cid", 3333),
+    ]);
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let info = mock_info("alice", &coins(TARGET, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(TARGET, DENOM));
+
+    // no recipient when there are beneficiaries
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: None,
+        vesting: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::BeneficiariesConfigured {});
+
+    // 1000 splits into 333 each, the first one gets the dust
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: None,
+        amount: Some(vec![coin(TARGET, DENOM)]),
+        vesting: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let sent: Vec<_> = res.messages.iter().map(|m| m.msg.clone()).collect();
+    assert_eq!(
+        sent,
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "ann".to_string(),
+                amount: coins(334, DENOM),
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "ben".to_string(),
+                amount: coins(333, DENOM),
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "cid".to_string(),
+                amount: coins(333, DENOM),
+            }),
+        ]
+    );
+
+    // the admin can change them
+    let msg = ExecuteMsg::UpdateBeneficiaries {
+        beneficiaries: vec![],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: None,
+        amount: None,
+        vesting: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::NoRecipient {});
+}
+
+#[test]
+fn milestone_releases() {
+    let mut deps = mock_dependencies();
+    let milestone = |description: &str, amount: u128| MilestoneMsg {
+        description: description.to_string(),
+        amount: Uint128::new(amount),
+        deadline: Expiration::AtHeight(mock_env().block.height + 1000),
+    };
+    let init = |milestones: Vec<MilestoneMsg>, threshold: Option<Threshold>| InitMsg {
+        milestones,
+        threshold,
+        ..init_msg()
+    };
+    let majority = Threshold::AbsolutePercentage {
+        percentage: Decimal::percent(50),
+    };
+
+    // milestones have to cover the target and come with a threshold
+    let msg = init(vec![milestone("alpha", 600)], Some(majority.clone()));
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidMilestones {});
+    let msg = init(vec![milestone("alpha", 600), milestone("beta", 400)], None);
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::ThresholdRequired {});
+
+    // and be voted on after the funding deadline, counted in the same unit
+    for deadline in [
+        Expiration::AtHeight(mock_env().block.height + 100),
+        Expiration::AtTime(mock_env().block.time.plus_seconds(10_000)),
+    ] {
+        let early = MilestoneMsg {
+            deadline,
+            ..milestone("beta", 400)
+        };
+        let msg = init(vec![milestone("alpha", 600), early], Some(majority.clone()));
+        let err =
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidDeadline {});
+    }
+
+    let msg = init(
+        vec![milestone("alpha", 600), milestone("beta", 400)],
+        Some(majority),
+    );
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let info = mock_info("alice", &coins(700, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    let info = mock_info("bob", &coins(300, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
     deps.querier
-        .update_balance(MOCK_CONTRACT_ADDR, coins(50000, native));
-    // pretend this is an old contract - set version explicitly
-    set_contract_version(deps.as_mut().storage, CONTRACT_NAME, MIGRATE_VERSION_3).unwrap();
+        .update_balance(mock_env().contract.address, coins(TARGET, DENOM));
+
+    // no sweeping the balance
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: None,
+        vesting: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::MilestonesPending {});
+    // nor closing the pool before every tranche is out
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Close {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MilestonesPending {});
+
+    let vote = |milestone_id: u64, approve: bool| ExecuteMsg::VoteMilestone {
+        milestone_id,
+        approve,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("carol", &[]),
+        vote(1, true),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoVotingWeight {});
+
+    // alice alone holds the majority
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        vote(1, true),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        vote(1, false),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidMilestoneStatus {
+            expected: MilestoneStatus::Voting,
+            status: MilestoneStatus::Passed
+        }
+    );
+
+    let release = ExecuteMsg::ReleaseMilestone {
+        milestone_id: 1,
+        recipient: Some("team".to_string()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        release.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(600, DENOM),
+        })
+    );
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), release).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidMilestoneStatus {
+            expected: MilestoneStatus::Passed,
+            status: MilestoneStatus::Released
+        }
+    );
+
+    // rejecting the second one fails the pool
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        vote(2, false),
+    )
+    .unwrap();
+    let res: MilestonesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Milestones {}).unwrap()).unwrap();
+    let statuses: Vec<_> = res.milestones.iter().map(|m| m.status).collect();
+    assert_eq!(
+        statuses,
+        vec![MilestoneStatus::Released, MilestoneStatus::Rejected]
+    );
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Failed);
+
+    // and the remaining 400 go back pro rata
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "bob".to_string(),
+            amount: coins(120, DENOM),
+        })
+    );
+}
+
+#[test]
+fn contributors_are_cw4_members() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    let mut later = mock_env();
+    later.block.height += 10;
+
+    // only the admin registers hooks
+    let msg = ExecuteMsg::AddHook {
+        addr: "multisig".to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
 
-    // channel state a bit lower (some in-flight acks)
-    let state = ChannelState {
-        // 14000 not accounted for (in-flight)
-        outstanding: Uint128::new(36000),
-        total_sent: Uint128::new(100000),
+    let info = mock_info("alice", &coins(300, DENOM));
+    let res = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    let hook = MemberChangedHookMsg::one(MemberDiff::new("alice", None, Some(300)));
+    assert_eq!(
+        res.messages[0].msg,
+        hook.into_cosmos_msg("multisig").unwrap()
+    );
+    let info = mock_info("bob", &coins(100, DENOM));
+    execute(deps.as_mut(), later.clone(), info, contribute()).unwrap();
+
+    let member = |addr: &str, at_height: Option<u64>| -> Option<u64> {
+        let msg = QueryMsg::Member {
+            addr: addr.to_string(),
+            at_height,
+        };
+        let res: MemberResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.weight
+    };
+    assert_eq!(member("alice", None), Some(300));
+    assert_eq!(member("bob", None), Some(100));
+    assert_eq!(member("bob", Some(later.block.height)), None);
+    assert_eq!(member("carol", None), None);
+
+    let total = |at_height: Option<u64>| -> u64 {
+        let msg = QueryMsg::TotalWeight { at_height };
+        let res: TotalWeightResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.weight
+    };
+    assert_eq!(total(None), 400);
+    assert_eq!(total(Some(later.block.height)), 300);
+
+    let msg = QueryMsg::ListMembers {
+        start_after: None,
+        limit: None,
     };
-    CHANNEL_STATE
-        .save(deps.as_mut().storage, (send_channel, native), &state)
-        .unwrap();
+    let res: MemberListResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(
+        res.members,
+        vec![
+            Member {
+                addr: "alice".to_string(),
+                weight: 300
+            },
+            Member {
+                addr: "bob".to_string(),
+                weight: 100
+            },
+        ]
+    );
+}
 
-    // run migration
-    migrate(
+#[test]
+fn cw4_weights_never_block_contributions() {
+    let mut deps = mock_dependencies();
+    let init = |tokens_per_weight: Option<u128>| InitMsg {
+        target: Uint128::MAX,
+        tokens_per_weight: tokens_per_weight.map(Uint128::new),
+        ..init_msg()
+    };
+    let err = instantiate(
         deps.as_mut(),
         mock_env(),
-        MigrateMsg {
-            default_gas_limit: Some(123456),
-        },
+        mock_info("creator", &[]),
+        init(Some(0)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ZeroTokensPerWeight {});
+
+    let weights = |deps: Deps, addr: &str| -> (Option<u64>, u64) {
+        let msg = QueryMsg::Member {
+            addr: addr.to_string(),
+            at_height: None,
+        };
+        let member: MemberResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        let msg = QueryMsg::TotalWeight { at_height: None };
+        let total: TotalWeightResponse =
+            from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        (member.weight, total.weight)
+    };
+
+    // 18 decimals overflow a u64 past ~18 tokens, weights are capped instead
+    let huge = 1_000_000_000_000_000_000_000u128;
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        init(None),
     )
     .unwrap();
+    let info = mock_info("alice", &coins(huge, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    let info = mock_info("bob", &coins(huge, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    assert_eq!(weights(deps.as_ref(), "bob"), (Some(u64::MAX), u64::MAX));
+
+    // or scaled down to fit
+    let mut deps = mock_dependencies();
+    let msg = init(Some(1_000_000_000_000));
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let info = mock_info("alice", &coins(huge, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    assert_eq!(
+        weights(deps.as_ref(), "alice"),
+        (Some(1_000_000_000), 1_000_000_000)
+    );
+}
+
+#[test]
+fn migrate_checks_version() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    let msg = || MigrateMsg { funding: None };
+
+    // same version is a no-op upgrade
+    migrate(deps.as_mut(), mock_env(), msg()).unwrap();
+    let version = cw2::get_contract_version(&deps.storage).unwrap();
+    assert_eq!(version.contract, "crates.io:pool");
+
+    cw2::set_contract_version(&mut deps.storage, "crates.io:pool", "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), msg()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CannotMigrateVersion {
+            previous_version: "99.0.0".to_string()
+        }
+    );
+
+    cw2::set_contract_version(&mut deps.storage, "crates.io:cw20-base", "0.1.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), msg()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CannotMigrate {
+            previous_contract: "crates.io:cw20-base".to_string()
+        }
+    );
+}
 
-    // check new channel state
-    let chan = query_channel(deps.as_ref(), send_channel.into()).unwrap();
-    assert_eq!(chan.balances, vec![Amount::native(50000, native)]);
-    assert_eq!(chan.total_sent, vec![Amount::native(114000, native)]);
+#[test]
+fn migrate_original_config() {
+    let mut deps = mock_dependencies();
+    cw2::set_contract_version(&mut deps.storage, LEGACY_CONTRACT_NAME, "0.16.0").unwrap();
+    let old = v1::ConfigV1 {
+        admin: Addr::unchecked(ADMIN),
+        title: "pool".to_string(),
+    };
+    v1::CONFIG_V1.save(&mut deps.storage, &old).unwrap();
+    // redirected to the pool before it tracked contributions
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, coins(400, DENOM));
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == "factory" => {
+            let cfg = FactoryConfigResponse {
+                admin: ADMIN.to_string(),
+                pool_code_id: 1,
+                receipt_nft: None,
+                creation_policy: CreationPolicy::Permissionless,
+                guardian: None,
+                paused: false,
+            };
+            SystemResult::Ok(ContractResult::Ok(to_binary(&cfg).unwrap()))
+        }
+        _ => panic!("unexpected query"),
+    });
 
-    // check config updates
-    let config = query_config(deps.as_ref()).unwrap();
-    assert_eq!(config.default_gas_limit, Some(123456));
-}
\ No newline at end of file
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { funding: None }).unwrap_err();
+    assert_eq!(err, ContractError::FundingRequired {});
+
+    let msg = MigrateMsg {
+        funding: Some(Funding {
+            target: Uint128::new(TARGET),
+            denom: DENOM.to_string(),
+            deadline: Expiration::AtHeight(mock_env().block.height + 100),
+            factory: Some("factory".to_string()),
+        }),
+    };
+    migrate(deps.as_mut(), mock_env(), msg).unwrap();
+
+    let cfg: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.admin, ADMIN);
+    assert_eq!(cfg.title, "pool");
+    assert_eq!(cfg.target, Uint128::new(TARGET));
+    assert_eq!(cfg.raised, Uint128::new(400));
+    assert_eq!(cfg.factory, Some("factory".to_string()));
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Open);
+    let version = cw2::get_contract_version(&deps.storage).unwrap();
+    assert_eq!(version.contract, "crates.io:pool");
+
+    // the factory keeps redirecting to the migrated pool
+    let msg = ExecuteMsg::Contribute {
+        on_behalf_of: Some("ann".to_string()),
+    };
+    let info = mock_info("factory", &coins(300, DENOM));
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, coins(700, DENOM));
+
+    // migrating again leaves the upgraded config alone
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { funding: None }).unwrap();
+
+    // short of the target, the balance held before goes back to the admin
+    assert_eq!(status(deps.as_ref(), after_deadline()), PoolStatus::Failed);
+    for (contributor, amount) in [(ADMIN, 400), ("ann", 300)] {
+        let info = mock_info(contributor, &[]);
+        let res = execute(deps.as_mut(), after_deadline(), info, ExecuteMsg::Refund {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: contributor.to_string(),
+                amount: coins(amount, DENOM),
+            })
+        );
+    }
+}
+
+#[test]
+fn migrate_funded_original_pool() {
+    let mut deps = mock_dependencies();
+    cw2::set_contract_version(&mut deps.storage, LEGACY_CONTRACT_NAME, "0.16.0").unwrap();
+    let old = v1::ConfigV1 {
+        admin: Addr::unchecked(ADMIN),
+        title: "pool".to_string(),
+    };
+    v1::CONFIG_V1.save(&mut deps.storage, &old).unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, coins(TARGET, DENOM));
+
+    // already holding the target, the admin can withdraw right away
+    let msg = MigrateMsg {
+        funding: Some(Funding {
+            target: Uint128::new(TARGET),
+            denom: DENOM.to_string(),
+            deadline: Expiration::AtHeight(mock_env().block.height + 100),
+            factory: None,
+        }),
+    };
+    migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(status(deps.as_ref(), mock_env()), PoolStatus::Succeeded);
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: None,
+        vesting: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(TARGET, DENOM),
+        })
+    );
+}
+
+#[test]
+fn guardian_pauses_contributions() {
+    let mut deps = mock_dependencies();
+    let msg = InitMsg {
+        guardian: Some("guardian".to_string()),
+        ..init_msg()
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+    let info = mock_info("ann", &coins(300, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+
+    // not even the admin can pause
+    let info = mock_info(ADMIN, &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let info = mock_info("guardian", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+    assert_eq!(res.attributes[0].value, "pause");
+
+    let cfg: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.guardian, Some("guardian".to_string()));
+    assert!(cfg.paused);
+
+    let info = mock_info("ann", &coins(300, DENOM));
+    let err = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "ann".to_string(),
+        amount: Uint128::new(300),
+        msg: to_binary(&ReceiveMsg::Contribute { on_behalf_of: None }).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("token", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+
+    // only the admin can unpause
+    let info = mock_info("guardian", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let info = mock_info(ADMIN, &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap();
+    let info = mock_info("ben", &coins(300, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+
+    // refunds still go through while paused
+    let info = mock_info("guardian", &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+    let info = mock_info("ann", &[]);
+    execute(deps.as_mut(), after_deadline(), info, ExecuteMsg::Refund {}).unwrap();
+}
+
+#[test]
+fn vesting_streams() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    let info = mock_info("ann", &coins(TARGET, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(TARGET, DENOM));
+
+    let height = mock_env().block.height;
+    let at = |blocks: u64| {
+        let mut env = mock_env();
+        env.block.height = height + blocks;
+        env
+    };
+    let withdraw =
+        |amount: Option<Vec<_>>, vesting: Option<VestingMsg>| ExecuteMsg::WithdrawFunds {
+            recipient: Some("team".to_string()),
+            amount,
+            vesting,
+        };
+    let vesting = |start: Expiration, end: Expiration, cliff: Option<Expiration>| {
+        Some(VestingMsg { start, end, cliff })
+    };
+
+    // must end after it starts, all in the same unit
+    for invalid in [
+        vesting(
+            Expiration::AtHeight(height + 100),
+            Expiration::AtHeight(height),
+            None,
+        ),
+        vesting(
+            Expiration::AtHeight(height),
+            Expiration::AtTime(mock_env().block.time.plus_seconds(100)),
+            None,
+        ),
+        vesting(
+            Expiration::AtHeight(height),
+            Expiration::AtHeight(height + 100),
+            Some(Expiration::AtHeight(height + 101)),
+        ),
+    ] {
+        let msg = withdraw(None, invalid);
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidVesting {});
+    }
+
+    // stream 600 over 100 blocks with a 20 block cliff
+    let schedule = vesting(
+        Expiration::AtHeight(height),
+        Expiration::AtHeight(height + 100),
+        Some(Expiration::AtHeight(height + 20)),
+    );
+    let msg = withdraw(Some(coins(600, DENOM)), schedule);
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert!(res.messages.is_empty());
+
+    // what is streaming cannot be withdrawn again
+    let msg = withdraw(None, None);
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(400, DENOM),
+        })
+    );
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(600, DENOM));
+
+    let claim = |deps: DepsMut, env: Env| {
+        execute(
+            deps,
+            env,
+            mock_info("team", &[]),
+            ExecuteMsg::ClaimVested {},
+        )
+    };
+    let err = claim(deps.as_mut(), at(10)).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+
+    let status = |deps: Deps, env: Env| -> VestingStatusResponse {
+        let msg = QueryMsg::VestingStatus {
+            recipient: "team".to_string(),
+        };
+        from_binary(&query(deps, env, msg).unwrap()).unwrap()
+    };
+    let res = status(deps.as_ref(), at(50));
+    assert_eq!(res.streams.len(), 1);
+    assert_eq!(res.streams[0].amount, Uint128::new(600));
+    assert_eq!(res.streams[0].vested, Uint128::new(300));
+
+    let res = claim(deps.as_mut(), at(50)).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(300, DENOM),
+        })
+    );
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(300, DENOM));
+
+    // only the admin cancels, the recipient keeps what vested
+    let msg = ExecuteMsg::CancelVesting {
+        recipient: "team".to_string(),
+    };
+    let err = execute(deps.as_mut(), at(75), mock_info("team", &[]), msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), at(75), mock_info(ADMIN, &[]), msg).unwrap();
+    let res = status(deps.as_ref(), at(200));
+    assert_eq!(res.streams[0].amount, Uint128::new(450));
+    assert_eq!(res.streams[0].claimed, Uint128::new(300));
+    let res: WithdrawnResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawn {}).unwrap()).unwrap();
+    assert_eq!(res.withdrawn, coins(850, DENOM));
+
+    // the unvested part can be withdrawn again, the rest stays claimable
+    let msg = withdraw(None, None);
+    let res = execute(deps.as_mut(), at(75), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(150, DENOM),
+        })
+    );
+    let res = claim(deps.as_mut(), at(200)).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(150, DENOM),
+        })
+    );
+    assert!(status(deps.as_ref(), at(200)).streams.is_empty());
+}
+
+#[test]
+fn cancelled_stream_stays_claimable() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+    let info = mock_info("ann", &coins(TARGET, DENOM));
+    execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(TARGET, DENOM));
+
+    let height = mock_env().block.height;
+    let at = |blocks: u64| {
+        let mut env = mock_env();
+        env.block.height = height + blocks;
+        env
+    };
+    let claim = |deps: DepsMut, env: Env| {
+        execute(
+            deps,
+            env,
+            mock_info("team", &[]),
+            ExecuteMsg::ClaimVested {},
+        )
+    };
+
+    // stream 600 over 100 blocks, claim half of it
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: Some(coins(600, DENOM)),
+        vesting: Some(VestingMsg {
+            start: Expiration::AtHeight(height),
+            end: Expiration::AtHeight(height + 100),
+            cliff: None,
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    claim(deps.as_mut(), at(50)).unwrap();
+
+    // cancelled with 330 vested, the 30 left are claimable before the original end
+    let msg = ExecuteMsg::CancelVesting {
+        recipient: "team".to_string(),
+    };
+    execute(deps.as_mut(), at(55), mock_info(ADMIN, &[]), msg).unwrap();
+    let res = claim(deps.as_mut(), at(60)).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "team".to_string(),
+            amount: coins(30, DENOM),
+        })
+    );
+    let err = claim(deps.as_mut(), at(70)).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+}
+
+#[test]
+fn share_token() {
+    let mut deps = mock_dependencies();
+    let msg = InitMsg {
+        allowed_denoms: vec!["ujuno".to_string()],
+        share_token: Some(ShareTokenMsg {
+            code_id: 3,
+            name: "Pool shares".to_string(),
+            symbol: "POOL".to_string(),
+            decimals: 6,
+        }),
+        ..init_msg()
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let init = Cw20InstantiateMsg {
+        name: "Pool shares".to_string(),
+        symbol: "POOL".to_string(),
+        decimals: 6,
+        initial_balances: vec![],
+        mint: Some(MinterResponse {
+            minter: mock_env().contract.address.to_string(),
+            cap: None,
+        }),
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                admin: None,
+                code_id: 3,
+                msg: to_binary(&init).unwrap(),
+                funds: vec![],
+                label: "pool shares".to_string(),
+            },
+            SHARE_TOKEN_REPLY_ID,
+        )]
+    );
+
+    // protobuf encoded MsgInstantiateContractResponse
+    let mut data = vec![0x0a, 6];
+    data.extend_from_slice(b"shares");
+    let result = SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: Some(Binary(data)),
+    });
+    let id = SHARE_TOKEN_REPLY_ID;
+    reply(deps.as_mut(), mock_env(), Reply { id, result }).unwrap();
+    let cfg: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.share_token, Some("shares".to_string()));
+
+    // only target denom contributions are minted shares
+    let info = mock_info("alice", &coins(300, DENOM));
+    let res = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "shares".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "alice".to_string(),
+                amount: Uint128::new(300),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    let info = mock_info("alice", &coins(300, "ujuno"));
+    let res = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    assert!(res.messages.is_empty());
+
+    // other tokens cannot be redeemed, and shares only once the pool is closed
+    let redeem = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "alice".to_string(),
+        amount: Uint128::new(100),
+        msg: to_binary(&ReceiveMsg::Redeem {}).unwrap(),
+    });
+    let info = mock_info("token", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, redeem.clone()).unwrap_err();
+    assert_eq!(err, ContractError::NotShareToken {});
+    let info = mock_info("shares", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, redeem).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidStatus {
+            expected: PoolStatus::Closed,
+            status: PoolStatus::Open,
+        }
+    );
+}
+
+#[test]
+fn contribution_hooks() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut());
+
+    // only the admin registers hooks
+    let msg = ExecuteMsg::AddContributionHook {
+        addr: "leaderboard".to_string(),
+    };
+    let info = mock_info("alice", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let res: HooksResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ContributionHooks {}).unwrap())
+            .unwrap();
+    assert_eq!(res.hooks, vec!["leaderboard".to_string()]);
+    // cw4 hooks are kept apart, they expect member diffs
+    let res: HooksResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Hooks {}).unwrap()).unwrap();
+    assert!(res.hooks.is_empty());
+
+    let hook = |change, contributor: &str, amount: u128, pool_total: u128| {
+        let msg = ContributionHookMsg {
+            change,
+            contributor: contributor.to_string(),
+            amount: Uint128::new(amount),
+            denom: DENOM.to_string(),
+            pool_total: Uint128::new(pool_total),
+        };
+        let msg = msg.into_cosmos_msg("leaderboard").unwrap();
+        SubMsg::reply_on_error(msg, CONTRIBUTION_HOOK_REPLY_ID)
+            .with_gas_limit(CONTRIBUTION_HOOK_GAS_LIMIT)
+    };
+
+    // funds sent along are already in the balance
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, coins(TARGET, DENOM));
+    let info = mock_info("alice", &coins(TARGET, DENOM));
+    let res = execute(deps.as_mut(), mock_env(), info, contribute()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![hook(ContributionChange::Contributed, "alice", TARGET, 1000)]
+    );
+
+    // withdrawals are not sent yet, so they are taken out of the total
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: Some(coins(300, DENOM)),
+        vesting: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[1..],
+        [hook(ContributionChange::Withdrawn, "team", 300, 700)]
+    );
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, coins(700, DENOM));
+
+    // streams stay in the pool, but do not count anymore
+    let height = mock_env().block.height;
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: Some(coins(200, DENOM)),
+        vesting: Some(VestingMsg {
+            start: Expiration::AtHeight(height),
+            end: Expiration::AtHeight(height + 100),
+            cliff: None,
+        }),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![hook(ContributionChange::Withdrawn, "team", 200, 500)]
+    );
+
+    // a failing hook does not revert what it reports
+    let result = SubMsgResult::Err("leaderboard is down".to_string());
+    let id = CONTRIBUTION_HOOK_REPLY_ID;
+    let res = reply(deps.as_mut(), mock_env(), Reply { id, result }).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "contribution_hook_failed"),
+            attr("error", "leaderboard is down"),
+        ]
+    );
+
+    let msg = ExecuteMsg::RemoveContributionHook {
+        addr: "leaderboard".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let msg = ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: Some(coins(100, DENOM)),
+        vesting: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+}
@@ -1,22 +1,35 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{ to_binary,  Binary, Deps, DepsMut, Env,  MessageInfo, Response, StdError, StdResult, SubMsgResponse, Reply,};
-use cw2::set_contract_version;
+use cosmwasm_std::{ to_binary,  Binary, Deps, DepsMut, Env,  MessageInfo, Response, StdError, StdResult, Reply,};
+use cw2::{get_contract_version, set_contract_version};
+use semver::Version;
 
 use crate::error::ContractError;
-use crate::execute::{ execute_redirect_funds, execute_create_pool};
+use crate::execute::{
+    execute_accept_admin, execute_close_round, execute_collect_fees, execute_create_pool,
+    execute_create_round, execute_migrate_pools, execute_pause, execute_receive,
+    execute_redirect_funds, execute_transfer_admin, execute_unpause, execute_update_config,
+    execute_update_creators,
+};
 use crate::helpers::unwrap_reply;
+use crate::migrations::{v1, LEGACY_CONTRACT_NAME};
 use crate::msg::{  ExecuteMsg,  MigrateMsg,  QueryMsg, InitMsg, };
-use crate::query::{query_config, query_pool};
-use crate::reply::{handle_instantiate_reply, handle_transfer_reply};
-use crate::state::{Config, CONFIG};
+use crate::query::{
+    query_collected_fees, query_config, query_contributor_totals, query_fee_info,
+    query_list_creators, query_list_pools, query_pending_admin, query_pool, query_pool_by_address,
+    query_pool_info, query_pool_totals, query_pools_by_creator, query_predict_pool_address,
+    query_round, query_round_matches,
+};
+use crate::reply::{handle_instantiate_reply, handle_migrate_reply, handle_transfer_reply};
+use crate::state::{Config, CONFIG, MAX_FEE_BPS};
 
 // version info for migration info
-const CONTRACT_NAME: &str = "crates.io:cw20-ics20";
+const CONTRACT_NAME: &str = "crates.io:poolFactory";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub const INSTANTIATE_REPLY_ID:u64=0;
 pub const REDIRECT_FUNDS_ID: u64 = 1;
+pub const MIGRATE_REPLY_ID: u64 = 2;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -31,9 +44,28 @@ pub fn instantiate(
     // let valid_pool_addr = deps.api.addr_validate(msg.pool_addr.as_str())?;
 
 
+    if msg.fee_bps >= MAX_FEE_BPS {
+        return Err(ContractError::InvalidFee {});
+    }
+    let fee_collector = match msg.fee_collector {
+        Some(addr) => deps.api.addr_validate(&addr)?,
+        None => valid_admin.clone(),
+    };
+
     let cfg = Config {
         admin: valid_admin,
-        pool_code_id: msg.pool_code_id
+        pool_code_id: msg.pool_code_id,
+        receipt_nft: msg
+            .receipt_nft
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?,
+        fee_bps: msg.fee_bps,
+        fee_collector,
+        creation_policy: msg.creation_policy,
+        guardian: msg
+            .guardian
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?,
     };
     
     CONFIG.save(deps.storage, &cfg)?;
@@ -49,29 +81,145 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::CreatePool {title  } => execute_create_pool(deps, env, info, title),
+        ExecuteMsg::CreatePool {
+            title,
+            target,
+            denom,
+            deadline,
+            allowed_denoms,
+            beneficiaries,
+            milestones,
+            threshold,
+            share_token,
+            tokens_per_weight,
+        } => execute_create_pool(
+            deps,
+            env,
+            info,
+            title,
+            target,
+            denom,
+            deadline,
+            allowed_denoms,
+            beneficiaries,
+            milestones,
+            threshold,
+            share_token,
+            tokens_per_weight,
+        ),
         ExecuteMsg::RedirectFund { pool_id } => execute_redirect_funds(deps, env, info,pool_id),        
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::CollectFees {} => execute_collect_fees(deps, info),
+        ExecuteMsg::UpdateConfig {
+            pool_code_id,
+            receipt_nft,
+            fee_bps,
+            fee_collector,
+            creation_policy,
+            guardian,
+        } => execute_update_config(
+            deps,
+            info,
+            pool_code_id,
+            receipt_nft,
+            fee_bps,
+            fee_collector,
+            creation_policy,
+            guardian,
+        ),
+        ExecuteMsg::TransferAdmin { new_admin } => execute_transfer_admin(deps, info, new_admin),
+        ExecuteMsg::AcceptAdmin {} => execute_accept_admin(deps, info),
+        ExecuteMsg::UpdateCreators { add, remove } => {
+            execute_update_creators(deps, info, add, remove)
+        }
+        ExecuteMsg::MigratePools {
+            new_code_id,
+            msg,
+            start_after,
+            limit,
+        } => execute_migrate_pools(deps, info, new_code_id, msg, start_after, limit),
+        ExecuteMsg::Pause {} => execute_pause(deps, info),
+        ExecuteMsg::Unpause {} => execute_unpause(deps, info),
+        ExecuteMsg::CreateRound {
+            start,
+            end,
+            pool_ids,
+        } => execute_create_round(deps, env, info, start, end, pool_ids),
+        ExecuteMsg::CloseRound { round_id } => execute_close_round(deps, env, info, round_id),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(mut _deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    Ok(Response::new())
+pub fn migrate(mut deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let stored = get_contract_version(deps.storage)?;
+    let storage_version: Version = stored.version.parse()?;
+
+    // First, ensure we are working from an equal or older version of this contract
+    // wrong type
+    if stored.contract != CONTRACT_NAME && stored.contract != LEGACY_CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    // existing one is newer
+    if storage_version > version {
+        return Err(ContractError::CannotMigrateVersion {
+            previous_version: stored.version,
+        });
+    }
+
+    // a legacy factory may still hold the original config and bare pool addresses
+    if stored.contract == LEGACY_CONTRACT_NAME && CONFIG.load(deps.storage).is_err() {
+        v1::migrate_config(deps.branch())?;
+    }
+
+    // update contract version
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", stored.version))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::PoolAddress { pool_id } => to_binary(&query_pool(deps, pool_id)?),
         QueryMsg::Config{}=>to_binary(&query_config(deps)?),
+        QueryMsg::Pool { pool_id } => to_binary(&query_pool_info(deps, pool_id)?),
+        QueryMsg::PoolByAddress { addr } => to_binary(&query_pool_by_address(deps, addr)?),
+        QueryMsg::ContributorTotals { contributor } => {
+            to_binary(&query_contributor_totals(deps, contributor)?)
+        }
+        QueryMsg::PoolTotals { pool_id } => to_binary(&query_pool_totals(deps, pool_id)?),
+        QueryMsg::ListPools { start_after, limit } => {
+            to_binary(&query_list_pools(deps, start_after, limit)?)
+        }
+        QueryMsg::PoolsByCreator {
+            creator,
+            start_after,
+            limit,
+        } => to_binary(&query_pools_by_creator(deps, creator, start_after, limit)?),
+        QueryMsg::FeeInfo {} => to_binary(&query_fee_info(deps)?),
+        QueryMsg::CollectedFees {} => to_binary(&query_collected_fees(deps)?),
+        QueryMsg::PendingAdmin {} => to_binary(&query_pending_admin(deps)?),
+        QueryMsg::ListCreators { start_after, limit } => {
+            to_binary(&query_list_creators(deps, start_after, limit)?)
+        }
+        QueryMsg::PredictPoolAddress { creator, salt } => {
+            to_binary(&query_predict_pool_address(deps, env, creator, salt)?)
+        }
+        QueryMsg::Round { round_id } => to_binary(&query_round(deps, round_id)?),
+        QueryMsg::RoundMatches { round_id } => to_binary(&query_round_matches(deps, round_id)?),
  }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> StdResult<Response> {
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> StdResult<Response> {
     match reply.id {
         INSTANTIATE_REPLY_ID => handle_instantiate_reply(deps,  reply),
-        REDIRECT_FUNDS_ID => handle_transfer_reply(deps, unwrap_reply(reply).unwrap()),
+        REDIRECT_FUNDS_ID => handle_transfer_reply(deps, env, unwrap_reply(reply)?),
+        MIGRATE_REPLY_ID => handle_migrate_reply(deps, reply),
         id => Err(StdError::generic_err(format!("Unknown reply id: {}", id))),
     }
 }
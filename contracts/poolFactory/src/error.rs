@@ -1,14 +1,9 @@
-use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
 use cosmwasm_std::StdError;
 use cw_utils::PaymentError;
 
-/// Never is a placeholder to ensure we don't return any errors
-#[derive(Error, Debug)]
-pub enum Never {}
-
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
@@ -29,8 +24,47 @@ pub enum ContractError {
     #[error("Only the governance contract can do this")]
     Unauthorized,
 
+    #[error("Fee must be below 10000 basis points")]
+    InvalidFee {},
+
+    #[error("No fees to collect")]
+    NoFees {},
+
+    #[error("No admin transfer in progress")]
+    NoPendingAdmin {},
+
+    #[error("Factory is paused")]
+    Paused {},
+
+    #[error("Rounds need at least one pool, no duplicates, and a start before an unexpired end")]
+    InvalidRound {},
+
+    #[error("Round {round_id} not found")]
+    RoundNotFound { round_id: u64 },
+
+    #[error("Round has not ended yet")]
+    RoundNotEnded {},
+
+    #[error("Round is already closed")]
+    RoundClosed {},
+
     #[error("You can only send cw20 tokens that have been explicitly allowed by governance")]
     NotOnAllowList,
+
+    #[error("{0}")]
+    SemVer(String),
+
+    #[error("Cannot migrate from different contract type: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from unsupported version: {previous_version}")]
+    CannotMigrateVersion { previous_version: String },
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
 
 impl From<FromUtf8Error> for ContractError {
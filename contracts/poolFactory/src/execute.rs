@@ -1,6 +1,30 @@
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, SubMsg, WasmMsg, to_binary, ReplyOn, BankMsg, coins};
+use cosmwasm_std::{
+    from_binary, Addr, Api, Binary, Coin, CosmosMsg, DepsMut, Empty, Env, MessageInfo, Order,
+    Response, StdResult, Storage, SubMsg, WasmMsg, to_binary, ReplyOn, BankMsg, Uint128,
+};
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use pool_types::helpers::PoolContract;
+use pool_types::pool::{ExecuteMsg as PoolExecuteMsg, PoolStatus, ReceiveMsg as PoolReceiveMsg};
+use cw_storage_plus::Bound;
+use cw_utils::{nonpayable, one_coin, Expiration, Threshold};
 
-use crate::{ContractError, state::{CONFIG, next_id, POOLS, CONTRIB}, msg::InitPoolMsg, contract::{REDIRECT_FUNDS_ID, INSTANTIATE_REPLY_ID}};
+use crate::amount::Amount;
+use crate::contract::{INSTANTIATE_REPLY_ID, MIGRATE_REPLY_ID, REDIRECT_FUNDS_ID};
+use crate::error::ContractError;
+use crate::helpers::pool_salt;
+use crate::msg::{Beneficiary, InitPoolMsg, MilestoneMsg, ReceiveMsg, ShareTokenMsg};
+use crate::state::{
+    next_id, next_round_id, pools, round_matches, take_fee, CreationPolicy, PendingMigration,
+    PendingPool, Redirect, Round, CONFIG, CREATORS, FEES, MAX_FEE_BPS, PAUSED, PENDING_ADMIN,
+    PENDING_MIGRATIONS, PENDING_POOL, PENDING_REDIRECT, POOL_ROUNDS, ROUNDS,
+};
+
+fn ensure_not_paused(storage: &dyn Storage) -> Result<(), ContractError> {
+    if PAUSED.may_load(storage)?.unwrap_or_default() {
+        return Err(ContractError::Paused {});
+    }
+    Ok(())
+}
 
 // check if pool_id exists and send fund there
 pub fn execute_redirect_funds(
@@ -9,66 +33,556 @@ pub fn execute_redirect_funds(
     _info: MessageInfo,
     pool_id: u64,
 ) -> Result<Response, ContractError> {
+    ensure_not_paused(_deps.storage)?;
     if _info.funds.is_empty() {
         return Err(ContractError::NoFunds {});
     }
 
-    let pool = POOLS.may_load(_deps.storage, pool_id).unwrap();
-    match pool {
-        Some(pool) => {
-            // storing sender for reply msg
-            CONTRIB.save(_deps.storage, &_info.sender.to_string())?;
-            Ok(Response::new().add_submessage(SubMsg {
-                // Instantiate Pool
-                msg: BankMsg::Send { to_address: pool.to_string(), amount: coins(_info.funds[0].amount.u128(), &_info.funds[0].denom )} .into(),
-                gas_limit: None,
-                id: REDIRECT_FUNDS_ID,
-                reply_on: ReplyOn::Success,
-            }))
-        },
-        None => {
-            Err(ContractError::PoolNotFound { pool_id })
+    let pool = pools()
+        .may_load(_deps.storage, pool_id)?
+        .ok_or(ContractError::PoolNotFound { pool_id })?;
+    // Forward everything but the fee to the pool
+    let cfg = CONFIG.load(_deps.storage)?;
+    let mut funds = vec![];
+    let mut fees = vec![];
+    for coin in _info.funds {
+        let (net, fee) = take_fee(_deps.storage, cfg.fee_bps, coin)?;
+        fees.push(Coin::new(fee.u128(), net.denom.clone()));
+        funds.push(net);
+    }
+    // the pool takes a single coin per contribution
+    let messages = funds
+        .iter()
+        .map(|coin| -> StdResult<_> {
+            let msg = PoolExecuteMsg::Contribute {
+                on_behalf_of: Some(_info.sender.to_string()),
+            };
+            Ok(WasmMsg::Execute {
+                contract_addr: pool.addr.to_string(),
+                msg: to_binary(&msg)?,
+                funds: vec![coin.clone()],
+            }
+            .into())
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let res = redirect(_deps, pool_id, _info.sender, funds, messages)?;
+    Ok(res.add_attribute("fee", join_coins(&fees)))
+}
+
+fn join_coins(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    let amount = Amount::Cw20(Cw20Coin {
+        address: info.sender.to_string(),
+        amount: wrapper.amount,
+    });
+    let contributor = deps.api.addr_validate(&wrapper.sender)?;
+    match msg {
+        ReceiveMsg::RedirectFund { pool_id } => {
+            ensure_not_paused(deps.storage)?;
+            if amount.is_empty() {
+                return Err(ContractError::NoFunds {});
+            }
+            let pool = pools()
+                .may_load(deps.storage, pool_id)?
+                .ok_or(ContractError::PoolNotFound { pool_id })?;
+            let cfg = CONFIG.load(deps.storage)?;
+            let coin = Coin {
+                denom: amount.denom(),
+                amount: amount.amount(),
+            };
+            let (net, fee) = take_fee(deps.storage, cfg.fee_bps, coin)?;
+            let contribute = PoolReceiveMsg::Contribute {
+                on_behalf_of: Some(contributor.to_string()),
+            };
+            let send = Cw20ExecuteMsg::Send {
+                contract: pool.addr.into(),
+                amount: net.amount,
+                msg: to_binary(&contribute)?,
+            };
+            let msg = WasmMsg::Execute {
+                contract_addr: info.sender.into(),
+                msg: to_binary(&send)?,
+                funds: vec![],
+            };
+            let fee = Coin::new(fee.u128(), net.denom.clone());
+            let res = redirect(deps, pool_id, contributor, vec![net], vec![msg.into()])?;
+            Ok(res.add_attribute("fee", fee.to_string()))
         }
-         
-        
     }
+}
 
-   
+/// Sends `messages` forwarding `funds` to the pool, the reply to the last one records them once
+/// they all succeeded
+fn redirect(
+    deps: DepsMut,
+    pool_id: u64,
+    contributor: Addr,
+    funds: Vec<Coin>,
+    mut messages: Vec<CosmosMsg>,
+) -> Result<Response, ContractError> {
+    // storing sender and funds for reply msg
+    PENDING_REDIRECT.save(
+        deps.storage,
+        &Redirect {
+            pool_id,
+            contributor,
+            funds,
+        },
+    )?;
+    let last = messages.pop().ok_or(ContractError::NoFunds {})?;
+    Ok(Response::new().add_messages(messages).add_submessage(SubMsg {
+        msg: last,
+        gas_limit: None,
+        id: REDIRECT_FUNDS_ID,
+        reply_on: ReplyOn::Success,
+    }))
 }
 
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_create_pool(
     _deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
-    title:String
+    title:String,
+    target: Uint128,
+    denom: String,
+    deadline: Expiration,
+    allowed_denoms: Vec<String>,
+    beneficiaries: Vec<Beneficiary>,
+    milestones: Vec<MilestoneMsg>,
+    threshold: Option<Threshold>,
+    share_token: Option<ShareTokenMsg>,
+    tokens_per_weight: Option<Uint128>,
 ) -> Result<Response, ContractError> {
-    // check if sender is admin
+    ensure_not_paused(_deps.storage)?;
 let cfg =CONFIG.load(_deps.storage)?;
+    let allowed = match cfg.creation_policy {
+        CreationPolicy::Permissionless => true,
+        CreationPolicy::Allowlist => {
+            _info.sender == cfg.admin || CREATORS.has(_deps.storage, &_info.sender)
+        }
+        CreationPolicy::AdminOnly => _info.sender == cfg.admin,
+    };
+    if !allowed {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let id = next_id(_deps.storage)?;
+    PENDING_POOL.save(
+        _deps.storage,
+        &PendingPool {
+            id,
+            title: title.clone(),
+            creator: _info.sender.clone(),
+            created_at_height: _env.block.height,
+            code_id: cfg.pool_code_id,
+        },
+    )?;
 
-let id = next_id(_deps.storage)?;
+    // deterministic address, see `QueryMsg::PredictPoolAddress`
+    let salt = pool_salt(&_info.sender, &id.to_be_bytes());
 
     Ok(Response::new().add_submessage(SubMsg {
         // Instantiate Pool
-        msg: WasmMsg::Instantiate {
-            admin: Some(_info.sender.to_string()),
+        msg: WasmMsg::Instantiate2 {
+            // the factory migrates the pools it created
+            admin: Some(_env.contract.address.to_string()),
             code_id: cfg.pool_code_id,
             msg: to_binary(&InitPoolMsg {
                 admin: cfg.admin.to_string(),
                 title: title.clone(),
-                // pool_id: id,
-                
+                target,
+                denom,
+                deadline,
+                allowed_denoms,
+                beneficiaries,
+                milestones,
+                threshold,
+                // pools answer to the same guardian
+                guardian: cfg.guardian.map(Into::into),
+                share_token,
+                // lets us forward redirected funds as contributions of their sender
+                factory: Some(_env.contract.address.to_string()),
+                tokens_per_weight,
             })?,
             funds: vec![],
             label: title,
+            salt: salt.clone(),
         }
         .into(),
         gas_limit: None,
         id: INSTANTIATE_REPLY_ID,
         reply_on: ReplyOn::Success,
-    }))
+    })
+    .add_attribute("action", "create_pool")
+    .add_attribute("pool_id", id.to_string())
+    .add_attribute("salt", salt.to_base64()))
 
     
 }
 
+pub fn execute_collect_fees(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let fees = FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Amount::from_parts(denom, amount)))
+        .collect::<StdResult<Vec<_>>>()?;
+    if fees.is_empty() {
+        return Err(ContractError::NoFees {});
+    }
+
+    // native coins in a single bank message, a transfer per cw20 token
+    let mut funds = vec![];
+    let mut messages = vec![];
+    let mut collected = vec![];
+    for fee in fees {
+        FEES.remove(deps.storage, &fee.denom());
+        collected.push(fee.to_string());
+        match fee {
+            Amount::Native(coin) => funds.push(coin),
+            token => messages.push(token.into_msg(cfg.fee_collector.to_string())?),
+        }
+    }
+    if !funds.is_empty() {
+        messages.insert(
+            0,
+            BankMsg::Send {
+                to_address: cfg.fee_collector.to_string(),
+                amount: funds,
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "collect_fees"),
+        ("fee_collector", cfg.fee_collector.as_str()),
+        ("amount", &collected.join(",")),
+    ]))
+}
+
+/// An empty address unsets the setting
+fn optional_addr(api: &dyn Api, addr: &str) -> StdResult<Option<Addr>> {
+    match addr.is_empty() {
+        true => Ok(None),
+        false => api.addr_validate(addr).map(Some),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_code_id: Option<u64>,
+    receipt_nft: Option<String>,
+    fee_bps: Option<u16>,
+    fee_collector: Option<String>,
+    creation_policy: Option<CreationPolicy>,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(pool_code_id) = pool_code_id {
+        cfg.pool_code_id = pool_code_id;
+    }
+    if let Some(receipt_nft) = receipt_nft {
+        cfg.receipt_nft = optional_addr(deps.api, &receipt_nft)?;
+    }
+    if let Some(fee_bps) = fee_bps {
+        if fee_bps >= MAX_FEE_BPS {
+            return Err(ContractError::InvalidFee {});
+        }
+        cfg.fee_bps = fee_bps;
+    }
+    if let Some(fee_collector) = fee_collector {
+        cfg.fee_collector = deps.api.addr_validate(&fee_collector)?;
+    }
+    if let Some(creation_policy) = creation_policy {
+        cfg.creation_policy = creation_policy;
+    }
+    if let Some(guardian) = guardian {
+        cfg.guardian = optional_addr(deps.api, &guardian)?;
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("pool_code_id", cfg.pool_code_id.to_string()))
+}
 
+pub fn execute_transfer_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_admin: String,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let new_admin = deps.api.addr_validate(&new_admin)?;
+    PENDING_ADMIN.save(deps.storage, &new_admin)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "transfer_admin")
+        .add_attribute("pending_admin", new_admin))
+}
+
+pub fn execute_accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending = PENDING_ADMIN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingAdmin {})?;
+    if info.sender != pending {
+        return Err(ContractError::Unauthorized {});
+    }
+    PENDING_ADMIN.remove(deps.storage);
+    CONFIG.update(deps.storage, |mut cfg| -> StdResult<_> {
+        cfg.admin = pending.clone();
+        Ok(cfg)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_admin")
+        .add_attribute("admin", pending))
+}
+
+pub fn execute_update_creators(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    for addr in add {
+        let addr = deps.api.addr_validate(&addr)?;
+        CREATORS.save(deps.storage, &addr, &Empty {})?;
+    }
+    for addr in remove {
+        let addr = deps.api.addr_validate(&addr)?;
+        CREATORS.remove(deps.storage, &addr);
+    }
+
+    Ok(Response::new().add_attribute("action", "update_creators"))
+}
+
+// settings for pagination
+const MAX_MIGRATIONS: u32 = 30;
+const DEFAULT_MIGRATIONS: u32 = 10;
+
+pub fn execute_migrate_pools(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_code_id: u64,
+    msg: Binary,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_MIGRATIONS).min(MAX_MIGRATIONS) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let pools = pools()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // replies come back in the order the migrations are sent
+    let mut migrations = vec![];
+    for (pool_id, pool) in pools.iter() {
+        PENDING_MIGRATIONS.push_back(
+            deps.storage,
+            &PendingMigration {
+                pool_id: *pool_id,
+                code_id: new_code_id,
+            },
+        )?;
+        migrations.push(SubMsg::reply_always(
+            WasmMsg::Migrate {
+                contract_addr: pool.addr.to_string(),
+                new_code_id,
+                msg: msg.clone(),
+            },
+            MIGRATE_REPLY_ID,
+        ));
+    }
+    let last = pools.last().map(|(pool_id, _)| pool_id.to_string());
+
+    Ok(Response::new()
+        .add_submessages(migrations)
+        .add_attribute("action", "migrate_pools")
+        .add_attribute("new_code_id", new_code_id.to_string())
+        .add_attribute("count", pools.len().to_string())
+        .add_attribute("last_pool_id", last.unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn execute_pause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if Some(&info.sender) != cfg.guardian.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+    PAUSED.save(deps.storage, &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "pause"),
+        ("sender", info.sender.as_str()),
+    ]))
+}
+
+pub fn execute_unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    PAUSED.save(deps.storage, &false)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "unpause"),
+        ("sender", info.sender.as_str()),
+    ]))
+}
+
+pub fn execute_create_round(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start: Expiration,
+    end: Expiration,
+    pool_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    let pot = one_coin(&info)?;
+    // mixed heights and times cannot be compared, and a round that never ends cannot be closed
+    if start.partial_cmp(&end) != Some(std::cmp::Ordering::Less)
+        || matches!(end, Expiration::Never {})
+        || end.is_expired(&env.block)
+    {
+        return Err(ContractError::InvalidRound {});
+    }
+    let mut sorted = pool_ids.clone();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.is_empty() || sorted.len() != pool_ids.len() {
+        return Err(ContractError::InvalidRound {});
+    }
+    for pool_id in pool_ids.iter() {
+        if !pools().has(deps.storage, *pool_id) {
+            return Err(ContractError::PoolNotFound { pool_id: *pool_id });
+        }
+    }
+
+    let round_id = next_round_id(deps.storage)?;
+    for pool_id in pool_ids.iter() {
+        POOL_ROUNDS.save(deps.storage, (*pool_id, round_id), &Empty {})?;
+    }
+    let round = Round {
+        sponsor: info.sender,
+        denom: pot.denom,
+        pot: pot.amount,
+        start,
+        end,
+        pool_ids,
+        closed: false,
+    };
+    ROUNDS.save(deps.storage, round_id, &round)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "create_round"),
+        ("round_id", &round_id.to_string()),
+        ("sponsor", round.sponsor.as_str()),
+        ("pot", &Coin::new(round.pot.u128(), round.denom).to_string()),
+    ]))
+}
+
+pub fn execute_close_round(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let mut round = ROUNDS
+        .may_load(deps.storage, round_id)?
+        .ok_or(ContractError::RoundNotFound { round_id })?;
+    if round.closed {
+        return Err(ContractError::RoundClosed {});
+    }
+    if !round.end.is_expired(&env.block) {
+        return Err(ContractError::RoundNotEnded {});
+    }
+
+    let mut messages = vec![];
+    let mut distributed = Uint128::zero();
+    for (pool_id, _, matched) in round_matches(deps.storage, round_id, &round)? {
+        POOL_ROUNDS.remove(deps.storage, (pool_id, round_id));
+        if matched.is_zero() {
+            continue;
+        }
+        let pool = PoolContract::new(pools().load(deps.storage, pool_id)?.addr);
+        let amount = vec![Coin::new(matched.u128(), round.denom.clone())];
+        let msg: CosmosMsg = match pool.status(&deps.querier)? {
+            // contributed on behalf of the sponsor, so a refund returns it if the pool fails
+            PoolStatus::Open if !pool.config(&deps.querier)?.paused => WasmMsg::Execute {
+                contract_addr: pool.addr().into(),
+                msg: to_binary(&PoolExecuteMsg::Contribute {
+                    on_behalf_of: Some(round.sponsor.to_string()),
+                })?,
+                funds: amount,
+            }
+            .into(),
+            PoolStatus::Succeeded => BankMsg::Send {
+                to_address: pool.addr().into(),
+                amount,
+            }
+            .into(),
+            // nobody could get the matching out of these, it goes back to the sponsor
+            _ => continue,
+        };
+        messages.push(msg);
+        distributed += matched;
+    }
+    // rounding dust, or the whole pot if nobody contributed to a live pool
+    let refund = round.pot - distributed;
+    if !refund.is_zero() {
+        messages.push(BankMsg::Send {
+            to_address: round.sponsor.to_string(),
+            amount: vec![Coin::new(refund.u128(), round.denom.clone())],
+        }
+        .into());
+    }
+    round.closed = true;
+    ROUNDS.save(deps.storage, round_id, &round)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "close_round"),
+        ("round_id", &round_id.to_string()),
+        ("distributed", &distributed.to_string()),
+        ("refund", &refund.to_string()),
+    ]))
+}
@@ -1,15 +1,23 @@
+use cosmwasm_std::{Addr, Binary, Event, Reply, StdError, StdResult, SubMsgResponse};
+use sha2::{Digest, Sha256};
 
-
-use cosmwasm_std::{Reply, StdResult, SubMsgResponse,Event,StdError};
-
+pub use pool_types::helpers::{factory_pool_id, PoolFactoryContract};
 
 pub fn unwrap_reply(reply: Reply) -> StdResult<SubMsgResponse> {
     reply.result.into_result().map_err(StdError::generic_err)
 }
 
 pub fn event_contains_attr(event: &Event, key: &str) -> bool {
-    event
-        .attributes
-        .iter()
-        .any(|attr| attr.key == key)
-}
\ No newline at end of file
+    event.attributes.iter().any(|attr| attr.key == key)
+}
+
+/// Instantiate2 salt binding a pool address to its creator. `CreatePool` uses the pool id as
+/// 8 big-endian bytes for `salt`.
+pub fn pool_salt(creator: &Addr, salt: &[u8]) -> Binary {
+    let mut hasher = Sha256::new();
+    // length prefixed, so no two creator/salt pairs hash the same input
+    hasher.update((creator.as_str().len() as u64).to_be_bytes());
+    hasher.update(creator.as_bytes());
+    hasher.update(salt);
+    Binary(hasher.finalize().to_vec())
+}
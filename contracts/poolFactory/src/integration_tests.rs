@@ -0,0 +1,663 @@
+#![cfg(test)]
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, coins, to_binary, Addr, Binary, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+    StdResult, Uint128,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_multi_test::{App, AppBuilder, Contract, ContractWrapper, Executor};
+use cw_storage_plus::Map;
+use cw_utils::Expiration;
+use pool_types::helpers::{PoolContract, PoolFactoryContract};
+
+use crate::contract::{execute, instantiate, query, reply};
+use crate::msg::{
+    ContributorTotalsResponse, CreationPolicy, Cw721ExecuteMsg, ExecuteMsg, InitMsg, PoolInfo,
+    PoolResponse, QueryMsg, ReceiptMetadata, ReceiptMintMsg, ReceiveMsg, ShareTokenMsg,
+};
+
+const ADMIN: &str = "admin";
+const CREATOR: &str = "creator";
+const BACKER: &str = "backer";
+const GUARDIAN: &str = "guardian";
+const DENOM: &str = "uatom";
+
+pub fn contract_pool_factory() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
+    Box::new(contract)
+}
+
+pub fn contract_pool() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        pool::contract::execute,
+        pool::contract::instantiate,
+        pool::contract::query,
+    )
+    .with_reply(pool::contract::reply);
+    Box::new(contract)
+}
+
+pub fn contract_pool_migratable() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        pool::contract::execute,
+        pool::contract::instantiate,
+        pool::contract::query,
+    )
+    .with_reply(pool::contract::reply)
+    .with_migrate(pool::contract::migrate);
+    Box::new(contract)
+}
+
+pub fn contract_cw20() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+/// Bare bones cw721 that only remembers what it minted, enough to check the receipts
+mod mock_cw721 {
+    use super::*;
+
+    pub const TOKENS: Map<&str, ReceiptMintMsg> = Map::new("tokens");
+
+    #[cw_serde]
+    pub enum QueryMsg {
+        Receipt { token_id: String },
+    }
+
+    pub fn instantiate(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _: Env,
+        _: MessageInfo,
+        msg: Cw721ExecuteMsg,
+    ) -> StdResult<Response> {
+        match msg {
+            Cw721ExecuteMsg::Mint(mint) => {
+                TOKENS.save(deps.storage, &mint.token_id.clone(), &mint)?;
+                Ok(Response::new())
+            }
+        }
+    }
+
+    pub fn query(deps: Deps, _: Env, msg: QueryMsg) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::Receipt { token_id } => to_binary(&TOKENS.load(deps.storage, &token_id)?),
+        }
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+fn mock_app(balance: Vec<Coin>) -> App {
+    AppBuilder::new().build(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked(BACKER), balance)
+            .unwrap();
+    })
+}
+
+/// Instantiates the factory (optionally minting receipts) and creates a single pool through it
+fn setup(app: &mut App, receipt_nft: Option<&Addr>, denom: &str) -> (Addr, Addr) {
+    let pool_code_id = app.store_code(contract_pool());
+    let factory_code_id = app.store_code(contract_pool_factory());
+
+    let msg = InitMsg {
+        admin: ADMIN.to_string(),
+        pool_code_id,
+        receipt_nft: receipt_nft.map(|addr| addr.to_string()),
+        fee_bps: 0,
+        fee_collector: None,
+        creation_policy: CreationPolicy::Permissionless,
+        guardian: Some(GUARDIAN.to_string()),
+    };
+    let factory = app
+        .instantiate_contract(
+            factory_code_id,
+            Addr::unchecked(ADMIN),
+            &msg,
+            &[],
+            "factory",
+            None,
+        )
+        .unwrap();
+
+    let msg = ExecuteMsg::CreatePool {
+        title: "pool".to_string(),
+        target: Uint128::new(1000),
+        denom: denom.to_string(),
+        deadline: Expiration::AtHeight(app.block_info().height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    app.execute_contract(Addr::unchecked(CREATOR), factory.clone(), &msg, &[])
+        .unwrap();
+    let pool: PoolResponse = app
+        .wrap()
+        .query_wasm_smart(&factory, &crate::msg::QueryMsg::PoolAddress { pool_id: 1 })
+        .unwrap();
+
+    (factory, Addr::unchecked(pool.pool_addr))
+}
+
+#[test]
+fn redirect_mints_receipt() {
+    let mut app = mock_app(coins(500, DENOM));
+    let nft_code_id = app.store_code(mock_cw721::contract());
+    let nft = app
+        .instantiate_contract(
+            nft_code_id,
+            Addr::unchecked(ADMIN),
+            &Empty {},
+            &[],
+            "receipts",
+            None,
+        )
+        .unwrap();
+    let (factory, pool) = setup(&mut app, Some(&nft), DENOM);
+
+    let msg = ExecuteMsg::RedirectFund { pool_id: 1 };
+    app.execute_contract(Addr::unchecked(BACKER), factory, &msg, &coins(300, DENOM))
+        .unwrap();
+    let balance = app.wrap().query_balance(&pool, DENOM).unwrap();
+    assert_eq!(balance.amount, Uint128::new(300));
+
+    let receipt: ReceiptMintMsg = app
+        .wrap()
+        .query_wasm_smart(
+            &nft,
+            &mock_cw721::QueryMsg::Receipt {
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(receipt.owner, BACKER);
+    assert_eq!(
+        receipt.extension,
+        ReceiptMetadata {
+            pool_id: 1,
+            amount: Uint128::new(300),
+            denom: DENOM.to_string(),
+        }
+    );
+}
+
+#[test]
+fn redirect_without_receipts() {
+    let mut app = mock_app(coins(500, DENOM));
+    let (factory, pool) = setup(&mut app, None, DENOM);
+
+    let msg = ExecuteMsg::RedirectFund { pool_id: 1 };
+    app.execute_contract(Addr::unchecked(BACKER), factory, &msg, &coins(300, DENOM))
+        .unwrap();
+    let balance = app.wrap().query_balance(&pool, DENOM).unwrap();
+    assert_eq!(balance.amount, Uint128::new(300));
+}
+
+#[test]
+fn paused_pool_blocks_redirects() {
+    let mut app = mock_app(coins(500, DENOM));
+    let (factory, pool) = setup(&mut app, None, DENOM);
+
+    // the pool answers to the factory guardian
+    let msg = pool_types::pool::ExecuteMsg::Pause {};
+    app.execute_contract(Addr::unchecked(GUARDIAN), pool.clone(), &msg, &[])
+        .unwrap();
+    let msg = ExecuteMsg::RedirectFund { pool_id: 1 };
+    app.execute_contract(
+        Addr::unchecked(BACKER),
+        factory.clone(),
+        &msg,
+        &coins(300, DENOM),
+    )
+    .unwrap_err();
+    let balance = app.wrap().query_balance(&pool, DENOM).unwrap();
+    assert_eq!(balance.amount, Uint128::zero());
+
+    let msg = pool_types::pool::ExecuteMsg::Unpause {};
+    app.execute_contract(Addr::unchecked(ADMIN), pool.clone(), &msg, &[])
+        .unwrap();
+    let msg = ExecuteMsg::RedirectFund { pool_id: 1 };
+    app.execute_contract(Addr::unchecked(BACKER), factory, &msg, &coins(300, DENOM))
+        .unwrap();
+    let balance = app.wrap().query_balance(&pool, DENOM).unwrap();
+    assert_eq!(balance.amount, Uint128::new(300));
+}
+
+#[test]
+fn round_match_refunded_if_pool_fails() {
+    let mut app = mock_app(coins(500, DENOM));
+    let (factory, pool) = setup(&mut app, None, DENOM);
+    let sponsor = Addr::unchecked("sponsor");
+    app.send_tokens(Addr::unchecked(BACKER), sponsor.clone(), &coins(200, DENOM))
+        .unwrap();
+    app.send_tokens(
+        Addr::unchecked(BACKER),
+        Addr::unchecked("carol"),
+        &coins(25, DENOM),
+    )
+    .unwrap();
+
+    let height = app.block_info().height;
+    let msg = ExecuteMsg::CreateRound {
+        start: Expiration::AtHeight(height),
+        end: Expiration::AtHeight(height + 10),
+        pool_ids: vec![1],
+    };
+    app.execute_contract(sponsor.clone(), factory.clone(), &msg, &coins(200, DENOM))
+        .unwrap();
+    let msg = ExecuteMsg::RedirectFund { pool_id: 1 };
+    for backer in [BACKER, "carol"] {
+        app.execute_contract(
+            Addr::unchecked(backer),
+            factory.clone(),
+            &msg,
+            &coins(25, DENOM),
+        )
+        .unwrap();
+    }
+
+    // the pool is still open when the round closes, the match is the sponsor's contribution
+    app.update_block(|block| block.height += 10);
+    let msg = ExecuteMsg::CloseRound { round_id: 1 };
+    app.execute_contract(Addr::unchecked("anyone"), factory, &msg, &[])
+        .unwrap();
+    let pool = PoolContract::new(pool);
+    let matched = pool.contribution(&app.wrap(), sponsor.as_str()).unwrap();
+    assert_eq!(matched.len(), 1);
+    assert!(!matched[0].amount.is_zero());
+
+    // so it comes back once the pool misses its target
+    app.update_block(|block| block.height += 100);
+    app.execute_contract(
+        sponsor.clone(),
+        pool.addr(),
+        &pool_types::pool::ExecuteMsg::Refund {},
+        &[],
+    )
+    .unwrap();
+    let balance = app.wrap().query_balance(&sponsor, DENOM).unwrap();
+    assert_eq!(balance.amount, Uint128::new(200));
+}
+
+#[test]
+fn pools_follow_factory_admin() {
+    let mut app = mock_app(vec![]);
+    let (factory, pool) = setup(&mut app, None, DENOM);
+
+    let msg = ExecuteMsg::TransferAdmin {
+        new_admin: "successor".to_string(),
+    };
+    app.execute_contract(Addr::unchecked(ADMIN), factory.clone(), &msg, &[])
+        .unwrap();
+    let msg = ExecuteMsg::AcceptAdmin {};
+    app.execute_contract(Addr::unchecked("successor"), factory, &msg, &[])
+        .unwrap();
+
+    // existing pools change hands along with the factory
+    let cfg = PoolContract::new(pool.clone()).config(&app.wrap()).unwrap();
+    assert_eq!(cfg.admin, "successor");
+    let msg = pool_types::pool::ExecuteMsg::Cancel {};
+    app.execute_contract(Addr::unchecked(ADMIN), pool.clone(), &msg, &[])
+        .unwrap_err();
+    app.execute_contract(Addr::unchecked("successor"), pool, &msg, &[])
+        .unwrap();
+}
+
+#[test]
+fn cw20_redirect_and_withdraw() {
+    let mut app = mock_app(vec![]);
+    let cw20_code_id = app.store_code(contract_cw20());
+    let msg = cw20_base::msg::InstantiateMsg {
+        name: "Token".to_string(),
+        symbol: "TKN".to_string(),
+        decimals: 6,
+        initial_balances: vec![Cw20Coin {
+            address: BACKER.to_string(),
+            amount: Uint128::new(2000),
+        }],
+        mint: None,
+        marketing: None,
+    };
+    let token = app
+        .instantiate_contract(
+            cw20_code_id,
+            Addr::unchecked(ADMIN),
+            &msg,
+            &[],
+            "token",
+            None,
+        )
+        .unwrap();
+    let denom = format!("cw20:{}", token);
+    let (factory, pool) = setup(&mut app, None, &denom);
+
+    // through the factory
+    let msg = Cw20ExecuteMsg::Send {
+        contract: factory.to_string(),
+        amount: Uint128::new(300),
+        msg: to_binary(&ReceiveMsg::RedirectFund { pool_id: 1 }).unwrap(),
+    };
+    app.execute_contract(Addr::unchecked(BACKER), token.clone(), &msg, &[])
+        .unwrap();
+    let totals: ContributorTotalsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &factory,
+            &QueryMsg::ContributorTotals {
+                contributor: BACKER.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(totals.totals, vec![coin(300, &denom)]);
+    // the pool records them against the backer
+    let contribution = PoolContract::new(pool.clone())
+        .contribution(&app.wrap(), BACKER)
+        .unwrap();
+    assert_eq!(contribution, vec![coin(300, &denom)]);
+
+    // straight to the pool, reaching the target
+    let msg = Cw20ExecuteMsg::Send {
+        contract: pool.to_string(),
+        amount: Uint128::new(1000),
+        msg: to_binary(&pool_types::pool::ReceiveMsg::Contribute { on_behalf_of: None }).unwrap(),
+    };
+    app.execute_contract(Addr::unchecked(BACKER), token.clone(), &msg, &[])
+        .unwrap();
+
+    // the admin withdraws all tokens
+    let msg = pool_types::pool::ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: None,
+        vesting: None,
+    };
+    app.execute_contract(Addr::unchecked(ADMIN), pool.clone(), &msg, &[])
+        .unwrap();
+    let query = |address: &str| -> Uint128 {
+        let res: BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                &token,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    assert_eq!(query("team"), Uint128::new(1300));
+    assert_eq!(query(pool.as_str()), Uint128::zero());
+}
+
+#[test]
+fn migrate_pools() {
+    let mut app = mock_app(vec![]);
+    let (factory, pool) = setup(&mut app, None, DENOM);
+    let old_code_id = app.contract_data(&pool).unwrap().code_id;
+
+    // the factory is the wasm admin of the pools it creates
+    assert_eq!(
+        app.contract_data(&pool).unwrap().admin,
+        Some(factory.clone())
+    );
+
+    let migrate = |code_id| ExecuteMsg::MigratePools {
+        new_code_id: code_id,
+        msg: to_binary(&pool_types::pool::MigrateMsg { funding: None }).unwrap(),
+        start_after: None,
+        limit: None,
+    };
+    let pool_code_id = |app: &App| {
+        let info: PoolInfo = app
+            .wrap()
+            .query_wasm_smart(&factory, &QueryMsg::Pool { pool_id: 1 })
+            .unwrap();
+        info.code_id
+    };
+
+    // only the factory admin may migrate
+    let new_code_id = app.store_code(contract_pool_migratable());
+    app.execute_contract(
+        Addr::unchecked(CREATOR),
+        factory.clone(),
+        &migrate(new_code_id),
+        &[],
+    )
+    .unwrap_err();
+
+    // a failing pool is reported and left on its old code
+    let broken_code_id = app.store_code(contract_pool());
+    let res = app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            factory.clone(),
+            &migrate(broken_code_id),
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &cosmwasm_std::Event::new("wasm")
+            .add_attribute("pool_id", "1")
+            .add_attribute("result", "failed")
+    ));
+    assert_eq!(pool_code_id(&app), old_code_id);
+    assert_eq!(app.contract_data(&pool).unwrap().code_id, old_code_id);
+
+    app.execute_contract(
+        Addr::unchecked(ADMIN),
+        factory.clone(),
+        &migrate(new_code_id),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(pool_code_id(&app), new_code_id);
+    assert_eq!(app.contract_data(&pool).unwrap().code_id, new_code_id);
+}
+
+#[test]
+fn pool_share_tokens() {
+    let mut app = mock_app(coins(1000, DENOM));
+    app.send_tokens(
+        Addr::unchecked(BACKER),
+        Addr::unchecked("friend"),
+        &coins(400, DENOM),
+    )
+    .unwrap();
+    let cw20_code_id = app.store_code(contract_cw20());
+    let pool_code_id = app.store_code(contract_pool());
+    let factory_code_id = app.store_code(contract_pool_factory());
+
+    let msg = InitMsg {
+        admin: ADMIN.to_string(),
+        pool_code_id,
+        receipt_nft: None,
+        fee_bps: 0,
+        fee_collector: None,
+        creation_policy: CreationPolicy::Permissionless,
+        guardian: None,
+    };
+    let factory = app
+        .instantiate_contract(
+            factory_code_id,
+            Addr::unchecked(ADMIN),
+            &msg,
+            &[],
+            "factory",
+            None,
+        )
+        .unwrap();
+    let msg = ExecuteMsg::CreatePool {
+        title: "pool".to_string(),
+        target: Uint128::new(1000),
+        denom: DENOM.to_string(),
+        deadline: Expiration::AtHeight(app.block_info().height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: Some(ShareTokenMsg {
+            code_id: cw20_code_id,
+            name: "Pool shares".to_string(),
+            symbol: "POOL".to_string(),
+            decimals: 6,
+        }),
+        tokens_per_weight: None,
+    };
+    app.execute_contract(Addr::unchecked(CREATOR), factory.clone(), &msg, &[])
+        .unwrap();
+    let info: PoolInfo = app
+        .wrap()
+        .query_wasm_smart(&factory, &QueryMsg::Pool { pool_id: 1 })
+        .unwrap();
+    let pool = info.addr;
+    let cfg: pool_types::pool::ConfigResponse = app
+        .wrap()
+        .query_wasm_smart(&pool, &pool_types::pool::QueryMsg::Config {})
+        .unwrap();
+    let shares = Addr::unchecked(cfg.share_token.unwrap());
+
+    // minted 1:1 for what they contributed
+    for (contributor, amount) in [(BACKER, 600), ("friend", 400)] {
+        let msg = pool_types::pool::ExecuteMsg::Contribute { on_behalf_of: None };
+        app.execute_contract(
+            Addr::unchecked(contributor),
+            pool.clone(),
+            &msg,
+            &coins(amount, DENOM),
+        )
+        .unwrap();
+    }
+    let balance = |app: &App, address: &str| -> Uint128 {
+        let res: BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                &shares,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    assert_eq!(balance(&app, BACKER), Uint128::new(600));
+    assert_eq!(balance(&app, "friend"), Uint128::new(400));
+
+    let redeem = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: pool.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_binary(&pool_types::pool::ReceiveMsg::Redeem {}).unwrap(),
+    };
+    // nothing to redeem before the pool is closed
+    app.execute_contract(Addr::unchecked(BACKER), shares.clone(), &redeem(300), &[])
+        .unwrap_err();
+
+    // the admin takes half and closes the pool
+    let msg = pool_types::pool::ExecuteMsg::WithdrawFunds {
+        recipient: Some("team".to_string()),
+        amount: Some(coins(500, DENOM)),
+        vesting: None,
+    };
+    app.execute_contract(Addr::unchecked(ADMIN), pool.clone(), &msg, &[])
+        .unwrap();
+    let msg = pool_types::pool::ExecuteMsg::Close {};
+    app.execute_contract(Addr::unchecked(ADMIN), pool.clone(), &msg, &[])
+        .unwrap();
+
+    // shares are transferable, whoever holds them gets the slice
+    let msg = Cw20ExecuteMsg::Transfer {
+        recipient: "buyer".to_string(),
+        amount: Uint128::new(200),
+    };
+    app.execute_contract(Addr::unchecked(BACKER), shares.clone(), &msg, &[])
+        .unwrap();
+    for (holder, amount, expected) in [
+        ("buyer", 200, 100),
+        ("friend", 400, 200),
+        (BACKER, 400, 200),
+    ] {
+        app.execute_contract(
+            Addr::unchecked(holder),
+            shares.clone(),
+            &redeem(amount),
+            &[],
+        )
+        .unwrap();
+        let received = app.wrap().query_balance(holder, DENOM).unwrap();
+        assert_eq!(received.amount, Uint128::new(expected));
+        assert_eq!(balance(&app, holder), Uint128::zero());
+    }
+    let left = app.wrap().query_balance(&pool, DENOM).unwrap();
+    assert_eq!(left.amount, Uint128::zero());
+}
+
+#[test]
+fn typed_helpers() {
+    let mut app = mock_app(coins(500, DENOM));
+    let (factory, first) = setup(&mut app, None, DENOM);
+    let factory = PoolFactoryContract::new(factory);
+
+    let msg = factory
+        .create_pool(
+            "second".to_string(),
+            Uint128::new(100),
+            DENOM.to_string(),
+            Expiration::AtHeight(app.block_info().height + 100),
+            vec![],
+            vec![],
+            vec![],
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    app.execute(Addr::unchecked(CREATOR), msg).unwrap();
+    let pool = factory.pool_contract(&app.wrap(), 2).unwrap();
+    assert_eq!(factory.pool(&app.wrap(), 2).unwrap().title, "second");
+    assert_eq!(factory.pool_id(&app.wrap(), &pool.addr()).unwrap(), Some(2));
+    assert_eq!(
+        factory
+            .pool_id(&app.wrap(), &Addr::unchecked("nobody"))
+            .unwrap(),
+        None
+    );
+    assert_eq!(factory.pool_address(&app.wrap(), 1).unwrap(), first);
+
+    // straight to the pool and through the factory
+    let msg = pool.contribute(coin(60, DENOM)).unwrap();
+    app.execute(Addr::unchecked(BACKER), msg).unwrap();
+    let msg = factory.redirect_fund(2, coins(40, DENOM)).unwrap();
+    app.execute(Addr::unchecked(BACKER), msg).unwrap();
+
+    // redirected funds count as the backer's own contribution
+    assert_eq!(
+        pool.contribution(&app.wrap(), BACKER).unwrap(),
+        coins(100, DENOM)
+    );
+    assert_eq!(
+        factory.contributor_totals(&app.wrap(), BACKER).unwrap(),
+        coins(40, DENOM)
+    );
+    assert_eq!(
+        factory.pool_totals(&app.wrap(), 2).unwrap(),
+        coins(40, DENOM)
+    );
+    assert_eq!(pool.config(&app.wrap()).unwrap().title, "second");
+    assert_eq!(
+        pool.status(&app.wrap()).unwrap(),
+        pool_types::pool::PoolStatus::Succeeded
+    );
+}
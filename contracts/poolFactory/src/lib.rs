@@ -1,4 +1,6 @@
+#![allow(non_snake_case)]
 
+pub mod amount;
 pub mod contract;
 mod error;
 
@@ -8,6 +10,10 @@ pub mod execute;
 pub mod query;
 pub mod reply;
 pub mod helpers;
-pub mod tests;
+pub mod migrations;
+
+#[cfg(test)]
+mod tests;
+mod integration_tests;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,77 @@
+// the factory was deployed under this name before it got one of its own
+pub const LEGACY_CONTRACT_NAME: &str = "crates.io:cw20-ics20";
+
+// the original factory only knew its admin and pool code, and kept bare pool addresses
+pub mod v1 {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{from_slice, Addr, DepsMut, Order, StdResult};
+    use cw_storage_plus::{Item, Map};
+    use serde::Deserialize;
+
+    use crate::state::{pools, Config, CreationPolicy, PoolInfo, CONFIG, POOL_IDS};
+    use crate::ContractError;
+
+    pub const CONFIG_V1: Item<ConfigV1> = Item::new("config");
+    pub const POOLS_V1: Map<u64, String> = Map::new("pools");
+    pub const CONTRIB_V1: Item<String> = Item::new("contributor");
+
+    #[cw_serde]
+    pub struct ConfigV1 {
+        pub admin: Addr,
+        pub pool_code_id: u64,
+    }
+
+    // all we need from the raw pool config, whatever its version
+    #[derive(Deserialize)]
+    struct PoolConfig {
+        title: String,
+    }
+
+    /// Extends the original config without fees or receipts, keeping pool creation open to
+    /// everyone as it was, and moves the pool addresses into the registry
+    pub fn migrate_config(deps: DepsMut) -> Result<(), ContractError> {
+        let old = CONFIG_V1.load(deps.storage)?;
+        let cfg = Config {
+            fee_collector: old.admin.clone(),
+            admin: old.admin,
+            pool_code_id: old.pool_code_id,
+            receipt_nft: None,
+            fee_bps: 0,
+            creation_policy: CreationPolicy::Permissionless,
+            guardian: None,
+        };
+        CONFIG.save(deps.storage, &cfg)?;
+        CONTRIB_V1.remove(deps.storage);
+
+        let old_pools = POOLS_V1
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (id, addr) in old_pools {
+            // the registry shares the namespace, so drop the bare entry before indexing
+            POOLS_V1.remove(deps.storage, id);
+            let addr = deps.api.addr_validate(&addr)?;
+            // pools were instantiated with their creator as wasm admin
+            let info = deps.querier.query_wasm_contract_info(&addr)?;
+            let creator = info.admin.unwrap_or(info.creator);
+            let title = deps
+                .querier
+                .query_wasm_raw(&addr, b"config".as_slice())?
+                .map(|raw| from_slice::<PoolConfig>(&raw))
+                .transpose()?
+                .map(|cfg| cfg.title)
+                .unwrap_or_default();
+            let pool = PoolInfo {
+                id,
+                addr,
+                title,
+                creator: deps.api.addr_validate(&creator)?,
+                // not recorded by the original factory
+                created_at_height: 0,
+                code_id: info.code_id,
+            };
+            pools().save(deps.storage, id, &pool)?;
+            POOL_IDS.save(deps.storage, &pool.addr, &id)?;
+        }
+        Ok(())
+    }
+}
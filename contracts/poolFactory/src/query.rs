@@ -1,7 +1,18 @@
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{StdResult, Deps, Addr};
+use cosmwasm_std::{instantiate2_address, Binary, Coin, Deps, Env, Order, StdError, StdResult};
+use cw_storage_plus::Bound;
+use cw_utils::maybe_addr;
 
-use crate::state::{CONFIG, POOLS};
+use crate::helpers::pool_salt;
+use crate::msg::{
+    CollectedFeesResponse, ConfigResponse, ContributorTotalsResponse, FeeInfoResponse,
+    ListCreatorsResponse, ListPoolsResponse, PendingAdminResponse, PoolResponse,
+    PoolTotalsResponse, PredictPoolAddressResponse, RoundMatch, RoundMatchesResponse,
+    RoundResponse,
+};
+use crate::state::{
+    pools, round_matches, PoolInfo, Round, CONFIG, CONTRIBUTOR_TOTALS, CREATORS,
+    FEES, PAUSED, PENDING_ADMIN, POOL_IDS, POOL_TOTALS, ROUNDS,
+};
 
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let cfg = CONFIG.load(deps.storage)?;
@@ -10,43 +21,201 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         
         admin:  cfg.admin.into(),
         pool_code_id: cfg.pool_code_id,
+        receipt_nft: cfg.receipt_nft.map(Into::into),
+        creation_policy: cfg.creation_policy,
+        guardian: cfg.guardian.map(Into::into),
+        paused: PAUSED.may_load(deps.storage)?.unwrap_or_default(),
         // pool_addr: cfg.pool_addr
     };
     Ok(res)
 }
 
 // settings for pagination
-// const MAX_LIMIT: u32 = 30;
-// const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
 
-#[cw_serde]
-pub struct ConfigResponse {
-    pub admin: String,
-    pub pool_code_id: u64,
+fn load_pool(deps: Deps, pool_id: u64) -> StdResult<PoolInfo> {
+    pools()
+        .may_load(deps.storage, pool_id)?
+        .ok_or_else(|| StdError::not_found(format!("pool {}", pool_id)))
 }
 
 pub fn query_pool(deps: Deps,pool_id:u64) -> StdResult<PoolResponse> {
-    let pool = POOLS.may_load(deps.storage, pool_id).unwrap();
-    match pool {
-        Some(pool) => {
-            let res = PoolResponse {
-                pool_id: pool_id,
-                pool_addr: pool,
-            };
-            Ok(res)
+    let pool = load_pool(deps, pool_id)?;
+    Ok(PoolResponse {
+        pool_id,
+        pool_addr: pool.addr.into(),
+    })
+}
+
+pub fn query_pool_info(deps: Deps, pool_id: u64) -> StdResult<PoolInfo> {
+    load_pool(deps, pool_id)
+}
+
+pub fn query_pool_by_address(deps: Deps, addr: String) -> StdResult<PoolInfo> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let pool_id = POOL_IDS
+        .may_load(deps.storage, &addr)?
+        .ok_or_else(|| StdError::not_found(format!("pool {}", addr)))?;
+    load_pool(deps, pool_id)
+}
+
+pub fn query_list_pools(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ListPoolsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let pools = pools()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, pool)| pool))
+        .collect::<StdResult<_>>()?;
+    Ok(ListPoolsResponse { pools })
+}
+
+pub fn query_pools_by_creator(
+    deps: Deps,
+    creator: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ListPoolsResponse> {
+    let creator = deps.api.addr_validate(&creator)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let pools = pools()
+        .idx
+        .creator
+        .prefix(creator)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, pool)| pool))
+        .collect::<StdResult<_>>()?;
+    Ok(ListPoolsResponse { pools })
+}
+
+pub fn query_contributor_totals(
+    deps: Deps,
+    contributor: String,
+) -> StdResult<ContributorTotalsResponse> {
+    let addr = deps.api.addr_validate(&contributor)?;
+    let totals = CONTRIBUTOR_TOTALS
+        .prefix(&addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect::<StdResult<_>>()?;
+    Ok(ContributorTotalsResponse {
+        contributor,
+        totals,
+    })
+}
+
+pub fn query_pool_totals(deps: Deps, pool_id: u64) -> StdResult<PoolTotalsResponse> {
+    load_pool(deps, pool_id)?;
+    let totals = POOL_TOTALS
+        .prefix(pool_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect::<StdResult<_>>()?;
+    Ok(PoolTotalsResponse { pool_id, totals })
+}
+
+pub fn query_fee_info(deps: Deps) -> StdResult<FeeInfoResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    Ok(FeeInfoResponse {
+        fee_bps: cfg.fee_bps,
+        fee_collector: cfg.fee_collector.into(),
+    })
+}
+
+pub fn query_collected_fees(deps: Deps) -> StdResult<CollectedFeesResponse> {
+    let fees = FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect::<StdResult<_>>()?;
+    Ok(CollectedFeesResponse { fees })
+}
+
+pub fn query_pending_admin(deps: Deps) -> StdResult<PendingAdminResponse> {
+    let pending_admin = PENDING_ADMIN.may_load(deps.storage)?.map(Into::into);
+    Ok(PendingAdminResponse { pending_admin })
+}
+
+pub fn query_list_creators(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListCreatorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = maybe_addr(deps.api, start_after)?;
+    let start = start.as_ref().map(Bound::exclusive);
+
+    let creators = CREATORS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(Into::into))
+        .collect::<StdResult<_>>()?;
+    Ok(ListCreatorsResponse { creators })
+}
+
+pub fn query_predict_pool_address(
+    deps: Deps,
+    env: Env,
+    creator: String,
+    salt: Binary,
+) -> StdResult<PredictPoolAddressResponse> {
+    let creator = deps.api.addr_validate(&creator)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    let checksum = deps.querier.query_wasm_code_info(cfg.pool_code_id)?.checksum;
+    let factory = deps.api.addr_canonicalize(env.contract.address.as_str())?;
+
+    let address = instantiate2_address(&checksum, &factory, &pool_salt(&creator, &salt))
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(PredictPoolAddressResponse {
+        address: deps.api.addr_humanize(&address)?.into(),
+    })
+}
+
+fn load_round(deps: Deps, round_id: u64) -> StdResult<Round> {
+    ROUNDS
+        .may_load(deps.storage, round_id)?
+        .ok_or_else(|| StdError::not_found(format!("Round {}", round_id)))
+}
+
+pub fn query_round(deps: Deps, round_id: u64) -> StdResult<RoundResponse> {
+    let round = load_round(deps, round_id)?;
+    Ok(RoundResponse {
+        id: round_id,
+        sponsor: round.sponsor.into(),
+        pot: Coin {
+            denom: round.denom,
+            amount: round.pot,
         },
-        None => {
-            let res = PoolResponse {
-                pool_id: pool_id,
-                pool_addr: "".to_string(),
-            };
-            Ok(res)
-        }
-    }
-}
-
-#[cw_serde]
-pub struct PoolResponse {
-    pub pool_id: u64,
-    pub pool_addr: String,
-}
\ No newline at end of file
+        start: round.start,
+        end: round.end,
+        pool_ids: round.pool_ids,
+        closed: round.closed,
+    })
+}
+
+pub fn query_round_matches(deps: Deps, round_id: u64) -> StdResult<RoundMatchesResponse> {
+    let round = load_round(deps, round_id)?;
+    let matches = round_matches(deps.storage, round_id, &round)?
+        .into_iter()
+        .map(|(pool_id, tally, matched)| RoundMatch {
+            pool_id,
+            contributors: tally.contributors,
+            total: tally.total,
+            matched,
+        })
+        .collect();
+    Ok(RoundMatchesResponse {
+        round_id,
+        denom: round.denom,
+        matches,
+    })
+}
+
@@ -1,31 +1,105 @@
-use std::{fmt::Debug, default};
+use cosmwasm_std::{
+    to_binary, DepsMut, Env, Reply, Response, StdError, StdResult, SubMsgResponse, SubMsgResult, WasmMsg,
+};
+use cw_utils::parse_reply_instantiate_data;
 
-use cosmwasm_std::{DepsMut, Reply, StdResult, Response, StdError, from_binary, Binary, SubMsgExecutionResponse, SubMsgResponse};
-use cw_utils::{parse_reply_instantiate_data, MsgExecuteContractResponse, parse_reply_execute_data};
-
-use crate::{state::{POOLS, POOL_COUNT, CONTRIB}, ContractError, helpers::event_contains_attr};
+use crate::msg::{Cw721ExecuteMsg, ReceiptMetadata, ReceiptMintMsg};
+use crate::state::{
+    next_receipt_id, pools, record_redirect, record_round_contribution, CONFIG, PENDING_MIGRATIONS, PENDING_POOL,
+    PENDING_REDIRECT, POOL_IDS,
+};
 
 // Handle the msg data and save the contract address
 // See: https://github.com/CosmWasm/cw-plus/blob/main/packages/utils/src/parse_reply.rs
 pub fn handle_instantiate_reply(_deps: DepsMut,msg: Reply) -> StdResult<Response> {
 
-    let res = parse_reply_instantiate_data(msg).unwrap();
+    let res = parse_reply_instantiate_data(msg)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
 
-    let count = POOL_COUNT.load(_deps.storage)?;
+    let pool_addr = _deps.api.addr_validate(&res.contract_address)?;
+    let pool = PENDING_POOL.load(_deps.storage)?.into_info(pool_addr);
+    PENDING_POOL.remove(_deps.storage);
 
-    POOLS.save(_deps.storage, count, &res.contract_address)?;
+    pools().save(_deps.storage, pool.id, &pool)?;
+    POOL_IDS.save(_deps.storage, &pool.addr, &pool.id)?;
 
-    Ok(Response::new().add_attribute("action","instantiated by factory").add_attribute("pool_addr", res.contract_address))
+    Ok(Response::new()
+        .add_attribute("action","instantiated by factory")
+        .add_attribute("pool_id", pool.id.to_string())
+        .add_attribute("pool_addr", pool.addr))
 }
 
 
 
-pub fn handle_transfer_reply(deps: DepsMut, _msg: SubMsgResponse) -> StdResult<Response> {
+pub fn handle_transfer_reply(
+    deps: DepsMut,
+    env: Env,
+    _msg: SubMsgResponse,
+) -> StdResult<Response> {
+
+    let redirect = PENDING_REDIRECT.load(deps.storage)?;
+    PENDING_REDIRECT.remove(deps.storage);
+    record_redirect(deps.storage, &redirect)?;
+    record_round_contribution(deps.storage, &env.block, &redirect)?;
 
-    Ok(Response::new().add_attribute("action","redirected").add_attribute("contributor",CONTRIB.load(deps.storage)?))
-    
-    // clean up CONTRIB
-    
-    // we could send some 721 to sender here
+    let amount = redirect
+        .funds
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
 
-}
\ No newline at end of file
+    // mint one receipt per redirected coin to the contributor
+    let mut mints = vec![];
+    if let Some(nft) = CONFIG.load(deps.storage)?.receipt_nft {
+        for coin in redirect.funds.iter() {
+            let mint = ReceiptMintMsg {
+                token_id: next_receipt_id(deps.storage)?.to_string(),
+                owner: redirect.contributor.to_string(),
+                token_uri: None,
+                extension: ReceiptMetadata {
+                    pool_id: redirect.pool_id,
+                    amount: coin.amount,
+                    denom: coin.denom.clone(),
+                },
+            };
+            mints.push(WasmMsg::Execute {
+                contract_addr: nft.to_string(),
+                msg: to_binary(&Cw721ExecuteMsg::Mint(mint))?,
+                funds: vec![],
+            });
+        }
+    }
+
+    Ok(Response::new()
+        .add_messages(mints)
+        .add_attribute("action","redirected")
+        .add_attribute("pool_id", redirect.pool_id.to_string())
+        .add_attribute("contributor", redirect.contributor)
+        .add_attribute("amount", amount))
+}
+
+/// Records the new code id of a migrated pool, or reports why it failed without reverting the batch
+pub fn handle_migrate_reply(deps: DepsMut, msg: Reply) -> StdResult<Response> {
+    let migration = PENDING_MIGRATIONS
+        .pop_front(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("no pending migration"))?;
+    let res = Response::new()
+        .add_attribute("action", "migrate_pool")
+        .add_attribute("pool_id", migration.pool_id.to_string())
+        .add_attribute("code_id", migration.code_id.to_string());
+
+    match msg.result {
+        SubMsgResult::Ok(_) => {
+            pools().update(deps.storage, migration.pool_id, |pool| -> StdResult<_> {
+                let mut pool = pool.ok_or_else(|| StdError::not_found("pool"))?;
+                pool.code_id = migration.code_id;
+                Ok(pool)
+            })?;
+            Ok(res.add_attribute("result", "migrated"))
+        }
+        SubMsgResult::Err(err) => Ok(res
+            .add_attribute("result", "failed")
+            .add_attribute("error", err)),
+    }
+}
@@ -1,18 +1,282 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Storage, StdResult, };
-use cw_storage_plus::{Item, Map, };
+use cosmwasm_std::{
+    Addr, BlockInfo, Coin, Decimal256, Empty, Order, Storage, StdResult, Uint128, Uint256,
+};
+use cw_storage_plus::{Deque, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::Expiration;
 
+use crate::msg::POOL_IDS_KEY;
+pub use crate::msg::{CreationPolicy, PoolInfo};
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const CONTRIB: Item<String> = Item::new("contributor");
+
+/// Full fee, in basis points
+pub const MAX_FEE_BPS: u16 = 10_000;
 pub const POOL_COUNT: Item<u64> = Item::new("pool_count");
+pub const RECEIPT_COUNT: Item<u64> = Item::new("receipt_count");
+
+/// set by the guardian to block pool creation and redirects
+pub const PAUSED: Item<bool> = Item::new("paused");
+
+/// proposed by `TransferAdmin`, becomes the admin once it accepts
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+
+/// addresses allowed to create pools under `CreationPolicy::Allowlist`
+pub const CREATORS: Map<&Addr, Empty> = Map::new("creators");
+
+// Used to pass info from execute_create_pool to the instantiate reply handler
+pub const PENDING_POOL: Item<PendingPool> = Item::new("pending_pool");
+
+/// reverse lookup of `pools()`, from pool address to pool id
+pub const POOL_IDS: Map<&Addr, u64> = Map::new(POOL_IDS_KEY);
+
+// Used to pass info from execute_migrate_pools to the migrate reply handler, one entry per
+// migration in the order they were sent
+pub const PENDING_MIGRATIONS: Deque<PendingMigration> = Deque::new("pending_migrations");
+
+// Used to pass info from execute_redirect_funds to the transfer reply handler
+pub const PENDING_REDIRECT: Item<Redirect> = Item::new("pending_redirect");
+
+/// indexed by (pool_id, contributor, denom) maintaining the total redirected in that currency
+pub const CONTRIBUTIONS: Map<(u64, &Addr, &str), Uint128> = Map::new("contributions");
+
+/// indexed by (pool_id, denom), sum of `CONTRIBUTIONS` over all contributors
+pub const POOL_TOTALS: Map<(u64, &str), Uint128> = Map::new("pool_totals");
+
+/// fees taken from redirected contributions and not collected yet, per denom
+pub const FEES: Map<&str, Uint128> = Map::new("fees");
+
+/// indexed by (contributor, denom), sum of `CONTRIBUTIONS` over all pools
+pub const CONTRIBUTOR_TOTALS: Map<(&Addr, &str), Uint128> = Map::new("contributor_totals");
+
+/// quadratic funding rounds sponsored through `CreateRound`
+pub const ROUNDS: Map<u64, Round> = Map::new("rounds");
+pub const ROUND_COUNT: Item<u64> = Item::new("round_count");
+
+/// indexed by (pool_id, round_id), the rounds a pool is matched in until they are closed
+pub const POOL_ROUNDS: Map<(u64, u64), Empty> = Map::new("pool_rounds");
+
+/// indexed by (round_id, pool_id, contributor), what they redirected in the round denom while
+/// the round was running
+pub const ROUND_CONTRIBUTIONS: Map<(u64, u64, &Addr), Uint128> = Map::new("round_contributions");
 
-pub const POOLS: Map<u64, String> = Map::new("pools");
+/// indexed by (round_id, pool_id), aggregate of `ROUND_CONTRIBUTIONS` over all contributors
+pub const ROUND_TALLIES: Map<(u64, u64), RoundTally> = Map::new("round_tallies");
 
 #[cw_serde]
 pub struct Config {
     pub admin: Addr,
     pub pool_code_id: u64,
+    pub receipt_nft: Option<Addr>,
+    /// cut of every redirected contribution, in basis points
+    pub fee_bps: u16,
+    /// where `CollectFees` sends the accumulated fees
+    pub fee_collector: Addr,
+    /// who can call `CreatePool`
+    pub creation_policy: CreationPolicy,
+    /// can pause the factory and the pools it creates, only the admin can unpause
+    pub guardian: Option<Addr>,
+}
+
+/// everything we know about a pool before its address is known
+#[cw_serde]
+pub struct PendingPool {
+    pub id: u64,
+    pub title: String,
+    pub creator: Addr,
+    pub created_at_height: u64,
+    pub code_id: u64,
+}
+
+#[cw_serde]
+pub struct PendingMigration {
+    pub pool_id: u64,
+    pub code_id: u64,
+}
+
+#[cw_serde]
+pub struct Redirect {
+    pub pool_id: u64,
+    pub contributor: Addr,
+    /// cw20 tokens are stored as `cw20:<contract>` denom, like everywhere in the ledger
+    pub funds: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct Round {
+    pub sponsor: Addr,
+    /// native denom of the pot, contributions in other denoms are not matched
+    pub denom: String,
+    pub pot: Uint128,
+    pub start: Expiration,
+    pub end: Expiration,
+    pub pool_ids: Vec<u64>,
+    /// set once `CloseRound` paid out the pot
+    pub closed: bool,
+}
+
+impl Round {
+    pub fn is_running(&self, block: &BlockInfo) -> bool {
+        self.start.is_expired(block) && !self.end.is_expired(block)
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct RoundTally {
+    /// unique contributors
+    pub contributors: u64,
+    pub total: Uint128,
+    /// sum of the square roots of every contributor's total
+    pub sqrt_sum: Decimal256,
+}
+
+impl RoundTally {
+    /// Quadratic funding match before scaling to the pot: the square of `sqrt_sum`, less what
+    /// the contributors put in themselves
+    pub fn ideal_match(&self) -> Uint256 {
+        (self.sqrt_sum * self.sqrt_sum)
+            .to_uint_floor()
+            .saturating_sub(self.total.into())
+    }
+}
+
+impl PendingPool {
+    pub fn into_info(self, addr: Addr) -> PoolInfo {
+        PoolInfo {
+            id: self.id,
+            addr,
+            title: self.title,
+            creator: self.creator,
+            created_at_height: self.created_at_height,
+            code_id: self.code_id,
+        }
+    }
+}
+
+pub struct PoolIndexes<'a> {
+    pub creator: MultiIndex<'a, Addr, PoolInfo, u64>,
+}
+
+impl<'a> IndexList<PoolInfo> for PoolIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PoolInfo>> + '_> {
+        let v: Vec<&dyn Index<PoolInfo>> = vec![&self.creator];
+        Box::new(v.into_iter())
+    }
+}
+
+/// every pool created by this factory, indexed by id and by creator
+pub fn pools<'a>() -> IndexedMap<'a, u64, PoolInfo, PoolIndexes<'a>> {
+    let indexes = PoolIndexes {
+        creator: MultiIndex::new(|_pk, pool| pool.creator.clone(), "pools", "pools__creator"),
+    };
+    IndexedMap::new("pools", indexes)
+}
+
+/// Adds a successful redirect to the contribution ledger and both running totals
+pub fn record_redirect(store: &mut dyn Storage, redirect: &Redirect) -> StdResult<()> {
+    let add = |amount: Uint128| move |total: Option<Uint128>| -> StdResult<_> {
+        Ok(total.unwrap_or_default() + amount)
+    };
+    for coin in redirect.funds.iter() {
+        let denom = coin.denom.as_str();
+        CONTRIBUTIONS.update(
+            store,
+            (redirect.pool_id, &redirect.contributor, denom),
+            add(coin.amount),
+        )?;
+        POOL_TOTALS.update(store, (redirect.pool_id, denom), add(coin.amount))?;
+        CONTRIBUTOR_TOTALS.update(store, (&redirect.contributor, denom), add(coin.amount))?;
+    }
+    Ok(())
+}
+
+fn sqrt(amount: Uint128) -> Decimal256 {
+    Decimal256::from_ratio(amount, 1u8).sqrt()
+}
+
+/// Tallies a successful redirect in every running round its pool is matched in
+pub fn record_round_contribution(
+    store: &mut dyn Storage,
+    block: &BlockInfo,
+    redirect: &Redirect,
+) -> StdResult<()> {
+    let round_ids = POOL_ROUNDS
+        .prefix(redirect.pool_id)
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for round_id in round_ids {
+        let round = ROUNDS.load(store, round_id)?;
+        if !round.is_running(block) {
+            continue;
+        }
+        let amount: Uint128 = redirect
+            .funds
+            .iter()
+            .filter(|coin| coin.denom == round.denom)
+            .map(|coin| coin.amount)
+            .sum();
+        if amount.is_zero() {
+            continue;
+        }
+
+        let key = (round_id, redirect.pool_id, &redirect.contributor);
+        let before = ROUND_CONTRIBUTIONS.may_load(store, key)?;
+        let after = before.unwrap_or_default() + amount;
+        ROUND_CONTRIBUTIONS.save(store, key, &after)?;
+
+        let mut tally = ROUND_TALLIES
+            .may_load(store, (round_id, redirect.pool_id))?
+            .unwrap_or_default();
+        if before.is_none() {
+            tally.contributors += 1;
+        }
+        tally.total += amount;
+        // swap the contributor's old root for the new one
+        tally.sqrt_sum = tally.sqrt_sum - sqrt(before.unwrap_or_default()) + sqrt(after);
+        ROUND_TALLIES.save(store, (round_id, redirect.pool_id), &tally)?;
+    }
+    Ok(())
+}
+
+/// Shares the pot of a round between its pools in proportion to their quadratic funding match.
+/// Returns the tally and the share of every pool, in the order of `round.pool_ids`.
+pub fn round_matches(
+    store: &dyn Storage,
+    round_id: u64,
+    round: &Round,
+) -> StdResult<Vec<(u64, RoundTally, Uint128)>> {
+    let tallies = round
+        .pool_ids
+        .iter()
+        .map(|pool_id| {
+            let tally = ROUND_TALLIES.may_load(store, (round_id, *pool_id))?;
+            Ok((*pool_id, tally.unwrap_or_default()))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let ideal_sum = tallies
+        .iter()
+        .fold(Uint256::zero(), |sum, (_, tally)| sum + tally.ideal_match());
+
+    tallies
+        .into_iter()
+        .map(|(pool_id, tally)| {
+            let matched = if ideal_sum.is_zero() {
+                Uint128::zero()
+            } else {
+                Uint256::from(round.pot)
+                    .multiply_ratio(tally.ideal_match(), ideal_sum)
+                    .try_into()?
+            };
+            Ok((pool_id, tally, matched))
+        })
+        .collect()
+}
+
+pub fn next_round_id(store: &mut dyn Storage) -> StdResult<u64> {
+    let id: u64 = ROUND_COUNT.may_load(store)?.unwrap_or_default() + 1;
+    ROUND_COUNT.save(store, &id)?;
+    Ok(id)
 }
 
 pub fn next_id(store: &mut dyn Storage) -> StdResult<u64> {
@@ -21,4 +285,23 @@ pub fn next_id(store: &mut dyn Storage) -> StdResult<u64> {
     Ok(id)
 }
 
+pub fn next_receipt_id(store: &mut dyn Storage) -> StdResult<u64> {
+    let id: u64 = RECEIPT_COUNT.may_load(store)?.unwrap_or_default() + 1;
+    RECEIPT_COUNT.save(store, &id)?;
+    Ok(id)
+}
 
+/// Carves the protocol fee out of `coin`, adding it to `FEES`. Returns what is left to forward.
+pub fn take_fee(store: &mut dyn Storage, fee_bps: u16, coin: Coin) -> StdResult<(Coin, Uint128)> {
+    let fee = coin.amount.multiply_ratio(fee_bps, MAX_FEE_BPS);
+    if !fee.is_zero() {
+        FEES.update(store, &coin.denom, |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default() + fee)
+        })?;
+    }
+    let net = Coin {
+        denom: coin.denom,
+        amount: coin.amount - fee,
+    };
+    Ok((net, fee))
+}
@@ -1,51 +1,1143 @@
+use crate::contract::{
+    execute, instantiate, migrate, query, reply, INSTANTIATE_REPLY_ID, MIGRATE_REPLY_ID,
+    REDIRECT_FUNDS_ID,
+};
+use crate::helpers::pool_salt;
+use crate::migrations::{v1, LEGACY_CONTRACT_NAME};
+use crate::msg::{
+    CollectedFeesResponse, ConfigResponse, ContributorTotalsResponse, CreationPolicy, ExecuteMsg,
+    FeeInfoResponse, InitMsg, InitPoolMsg, ListCreatorsResponse, ListPoolsResponse, MigrateMsg,
+    PendingAdminResponse, PoolInfo, PoolResponse, PoolTotalsResponse, PredictPoolAddressResponse,
+    QueryMsg, ReceiveMsg, RoundMatch, RoundMatchesResponse, RoundResponse,
+};
+use crate::ContractError;
 
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
+use cosmwasm_std::{
+    coin, coins, from_binary, instantiate2_address, to_binary, Addr, Api, BankMsg, Binary,
+    CanonicalAddr, CodeInfoResponse, Coin, ContractInfoResponse, ContractResult, CosmosMsg,
+    DepsMut, HexBinary, OwnedDeps, RecoverPubkeyError, Reply, ReplyOn, Response, StdError,
+    StdResult, SubMsgResponse, SubMsgResult, SystemResult, Uint128, VerificationError, WasmMsg,
+    WasmQuery,
+};
+use cw_utils::{Expiration, PaymentError};
+use pool_types::pool::{PoolStatus, StatusResponse};
 
+const DUMMY: &str = "cosmos1y4fu4qfxxs9yg2pec9ualrgr9wxfyt77k45e55";
 
-#[cfg(test)]
-mod tests {
-    
-    use crate::ContractError;
-    use crate::contract::{instantiate, query, execute};
-    use crate::msg::{InitMsg, QueryMsg,  ExecuteMsg};
-    use crate::query::ConfigResponse;
+/// protobuf encoded MsgInstantiateContractResponse as returned by wasmd
+fn instantiate_reply(contract_addr: &str) -> Reply {
+    let mut data = vec![0x0a, contract_addr.len() as u8];
+    data.extend_from_slice(contract_addr.as_bytes());
+    Reply {
+        id: INSTANTIATE_REPLY_ID,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(Binary(data)),
+        }),
+    }
+}
+
+fn init_msg(creation_policy: CreationPolicy) -> InitMsg {
+    InitMsg {
+        admin: DUMMY.to_string(),
+        pool_code_id: 7,
+        receipt_nft: None,
+        fee_bps: 0,
+        fee_collector: None,
+        creation_policy,
+        guardian: None,
+    }
+}
 
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary};
+/// instantiates the factory with `DUMMY` as admin and default settings
+fn setup(deps: DepsMut, creation_policy: CreationPolicy) {
+    let msg = init_msg(creation_policy);
+    instantiate(deps, mock_env(), mock_info("sender", &[]), msg).unwrap();
+}
 
-    const  DUMMY: &str = "cosmos1y4fu4qfxxs9yg2pec9ualrgr9wxfyt77k45e55";
+/// runs RedirectFund and the matching transfer reply
+fn redirect(deps: DepsMut, contributor: &str, pool_id: u64, funds: &[Coin]) -> Response {
+    let mut deps = deps;
+    let msg = ExecuteMsg::RedirectFund { pool_id };
+    let res = execute(
+        deps.branch(),
+        mock_env(),
+        mock_info(contributor, funds),
+        msg,
+    )
+    .unwrap();
+    let result = SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: None,
+    });
+    let id = REDIRECT_FUNDS_ID;
+    reply(deps, mock_env(), Reply { id, result }).unwrap();
+    res
+}
 
+/// what a redirect sends the pool for every coin
+fn contribution(pool: &str, contributor: &str, funds: Coin) -> CosmosMsg {
+    let msg = pool_types::pool::ExecuteMsg::Contribute {
+        on_behalf_of: Some(contributor.to_string()),
+    };
+    CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: pool.to_string(),
+        msg: to_binary(&msg).unwrap(),
+        funds: vec![funds],
+    })
+}
 
+/// runs CreatePool and the matching instantiate reply
+fn create_pool(deps: DepsMut, creator: &str, title: &str, contract_addr: &str) {
+    let msg = ExecuteMsg::CreatePool {
+        title: title.to_string(),
+        target: Uint128::new(1000),
+        denom: "uatom".to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    let mut deps = deps;
+    execute(deps.branch(), mock_env(), mock_info(creator, &[]), msg).unwrap();
+    reply(deps, mock_env(), instantiate_reply(contract_addr)).unwrap();
+}
 
 #[test]
 fn proper_init() {
     let mut deps = mock_dependencies();
     let info = mock_info("sender", &[]);
-    let msg = InitMsg {  admin: DUMMY.to_string(), pool_code_id: 0 };
-    
+    let msg = InitMsg {
+        admin: DUMMY.to_string(),
+        pool_code_id: 0,
+        receipt_nft: None,
+        fee_bps: 0,
+        fee_collector: None,
+        creation_policy: CreationPolicy::Permissionless,
+        guardian: None,
+    };
+
     let res = instantiate(deps.as_mut(), mock_env(), info, msg);
-        
+
     // println!("res: {:?}", res);
-    
+
     assert_eq!(0, res.unwrap().messages.len());
     // it worked, let's query the state
-    let cfg = query(deps.as_ref(), mock_env(), QueryMsg::Config {  }).unwrap();
-    
+    let cfg = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
     let value: ConfigResponse = from_binary(&cfg).unwrap();
 
     assert_eq!(DUMMY, value.admin);
     assert_eq!(0, value.pool_code_id);
-
 }
 
-fn create_a_pool(){
+#[test]
+fn create_a_pool() {
     let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+
     let info = mock_info("sender", &[]);
-    
+    let msg = ExecuteMsg::CreatePool {
+        title: "pool".to_string(),
+        target: Uint128::new(1000),
+        denom: "uatom".to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    assert_eq!(INSTANTIATE_REPLY_ID, res.messages[0].id);
+
+    // a reply without the instantiate response is an error, not a panic
+    let result = SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: None,
+    });
+    let id = INSTANTIATE_REPLY_ID;
+    let err = reply(deps.as_mut(), mock_env(), Reply { id, result }).unwrap_err();
+    assert!(matches!(err, StdError::GenericErr { .. }));
+}
+
+#[test]
+fn redirect_to_unknown_pool_fails() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+
+    let info = mock_info("sender", &coins(100, "uatom"));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::RedirectFund { pool_id: 3 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PoolNotFound { pool_id: 3 });
+}
+
+#[test]
+fn pool_registry() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+
+    create_pool(deps.as_mut(), "alice", "first", "pool1");
+    create_pool(deps.as_mut(), "bob", "second", "pool2");
+    create_pool(deps.as_mut(), "alice", "third", "pool3");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Pool { pool_id: 3 }).unwrap();
+    let pool: PoolInfo = from_binary(&res).unwrap();
+    assert_eq!(
+        pool,
+        PoolInfo {
+            id: 3,
+            addr: Addr::unchecked("pool3"),
+            title: "third".to_string(),
+            creator: Addr::unchecked("alice"),
+            created_at_height: mock_env().block.height,
+            code_id: 7,
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PoolAddress { pool_id: 2 },
+    )
+    .unwrap();
+    let pool: PoolResponse = from_binary(&res).unwrap();
+    assert_eq!(pool.pool_addr, "pool2");
+
+    // unknown ids are an error rather than an empty address
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PoolAddress { pool_id: 4 },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::not_found("pool 4"));
+
+    // reverse lookup
+    let msg = QueryMsg::PoolByAddress {
+        addr: "pool2".to_string(),
+    };
+    let pool: PoolInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(pool.id, 2);
+    let msg = QueryMsg::PoolByAddress {
+        addr: "pool4".to_string(),
+    };
+    let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    assert_eq!(err, StdError::not_found("pool pool4"));
+
+    let msg = QueryMsg::ListPools {
+        start_after: Some(1),
+        limit: Some(1),
+    };
+    let res: ListPoolsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let ids: Vec<_> = res.pools.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![2]);
+
+    let msg = QueryMsg::PoolsByCreator {
+        creator: "alice".to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let res: ListPoolsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let ids: Vec<_> = res.pools.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![1, 3]);
+
+    let msg = QueryMsg::PoolsByCreator {
+        creator: "alice".to_string(),
+        start_after: Some(1),
+        limit: None,
+    };
+    let res: ListPoolsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let ids: Vec<_> = res.pools.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![3]);
+}
+
+#[test]
+fn redirect_funds_ledger() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+    create_pool(deps.as_mut(), "alice", "first", "pool1");
+    create_pool(deps.as_mut(), "alice", "second", "pool2");
+
+    // all coins are contributed on behalf of the sender, not only the first one
+    let funds = vec![coin(100, "uatom"), coin(5, "ujuno")];
+    let res = redirect(deps.as_mut(), "bob", 1, &funds);
+    let messages: Vec<_> = res.messages.into_iter().map(|msg| msg.msg).collect();
+    assert_eq!(
+        messages,
+        vec![
+            contribution("pool1", "bob", coin(100, "uatom")),
+            contribution("pool1", "bob", coin(5, "ujuno")),
+        ]
+    );
+    redirect(deps.as_mut(), "bob", 2, &coins(50, "uatom"));
+    redirect(deps.as_mut(), "carol", 1, &coins(10, "uatom"));
+
+    let msg = QueryMsg::ContributorTotals {
+        contributor: "bob".to_string(),
+    };
+    let res: ContributorTotalsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.totals, vec![coin(150, "uatom"), coin(5, "ujuno")]);
+
+    let msg = QueryMsg::PoolTotals { pool_id: 1 };
+    let res: PoolTotalsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.totals, vec![coin(110, "uatom"), coin(5, "ujuno")]);
+
+    let msg = QueryMsg::PoolTotals { pool_id: 3 };
+    let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    assert_eq!(err, StdError::not_found("pool 3"));
+}
+
+#[test]
+fn protocol_fee() {
+    let mut deps = mock_dependencies();
+    let msg = InitMsg {
+        fee_bps: 10_000,
+        fee_collector: Some("treasury".to_string()),
+        ..init_msg(CreationPolicy::Permissionless)
+    };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("sender", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidFee {});
+
+    let msg = InitMsg {
+        fee_bps: 250,
+        fee_collector: Some("treasury".to_string()),
+        ..init_msg(CreationPolicy::Permissionless)
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("sender", &[]), msg).unwrap();
+    create_pool(deps.as_mut(), "alice", "first", "pool1");
+
+    let res: FeeInfoResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeInfo {}).unwrap()).unwrap();
+    assert_eq!(res.fee_bps, 250);
+    assert_eq!(res.fee_collector, "treasury");
+
+    // 2.5% stays with the factory, the pool gets the rest and only that is recorded
+    let res = redirect(
+        deps.as_mut(),
+        "bob",
+        1,
+        &[coin(1000, "uatom"), coin(10, "ujuno")],
+    );
+    let messages: Vec<_> = res.messages.into_iter().map(|msg| msg.msg).collect();
+    assert_eq!(
+        messages,
+        vec![
+            contribution("pool1", "bob", coin(975, "uatom")),
+            contribution("pool1", "bob", coin(10, "ujuno")),
+        ]
+    );
+    let msg = QueryMsg::PoolTotals { pool_id: 1 };
+    let res: PoolTotalsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.totals, vec![coin(975, "uatom"), coin(10, "ujuno")]);
+    redirect(deps.as_mut(), "carol", 1, &coins(200, "uatom"));
+
+    let res: CollectedFeesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap())
+            .unwrap();
+    assert_eq!(res.fees, coins(30, "uatom"));
+
+    // only the admin collects, and only once
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::CollectFees {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        ExecuteMsg::CollectFees {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(30, "uatom"),
+        })
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        ExecuteMsg::CollectFees {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoFees {});
+}
+
+#[test]
+fn admin_transfer_and_config() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+
+    let update = ExecuteMsg::UpdateConfig {
+        pool_code_id: Some(8),
+        receipt_nft: None,
+        fee_bps: None,
+        fee_collector: None,
+        creation_policy: Some(CreationPolicy::AdminOnly),
+        guardian: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info(DUMMY, &[]), update).unwrap();
+    let res: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(res.pool_code_id, 8);
+    assert_eq!(res.creation_policy, CreationPolicy::AdminOnly);
+
+    // set and unset again
+    let update = |value: &str| ExecuteMsg::UpdateConfig {
+        pool_code_id: None,
+        receipt_nft: Some(value.to_string()),
+        fee_bps: None,
+        fee_collector: None,
+        creation_policy: None,
+        guardian: Some(value.to_string()),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        update("nft"),
+    )
+    .unwrap();
+    let res: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(res.receipt_nft, Some("nft".to_string()));
+    assert_eq!(res.guardian, Some("nft".to_string()));
+    execute(deps.as_mut(), mock_env(), mock_info(DUMMY, &[]), update("")).unwrap();
+    let res: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(res.receipt_nft, None);
+    assert_eq!(res.guardian, None);
+    assert_eq!(res.pool_code_id, 8);
+
+    // nothing changes until the new admin accepts
+    let msg = ExecuteMsg::TransferAdmin {
+        new_admin: "alice".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(DUMMY, &[]), msg).unwrap();
+    let res: PendingAdminResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PendingAdmin {}).unwrap()).unwrap();
+    assert_eq!(res.pending_admin, Some("alice".to_string()));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap();
+
+    let res: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(res.admin, "alice");
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoPendingAdmin {});
+}
+
+#[test]
+fn creation_policy() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::AdminOnly);
+
+    let msg = ExecuteMsg::CreatePool {
+        title: "pool".to_string(),
+        target: Uint128::new(1000),
+        denom: "uatom".to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+
+    // allowlisted creators
+    let update = ExecuteMsg::UpdateConfig {
+        pool_code_id: None,
+        receipt_nft: None,
+        fee_bps: None,
+        fee_collector: None,
+        creation_policy: Some(CreationPolicy::Allowlist),
+        guardian: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(DUMMY, &[]), update).unwrap();
+    let creators = ExecuteMsg::UpdateCreators {
+        add: vec!["alice".to_string(), "bob".to_string()],
+        remove: vec![],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(DUMMY, &[]), creators).unwrap();
+    let creators = ExecuteMsg::UpdateCreators {
+        add: vec![],
+        remove: vec!["bob".to_string()],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(DUMMY, &[]), creators).unwrap();
+
+    let msg_list = QueryMsg::ListCreators {
+        start_after: None,
+        limit: None,
+    };
+    let res: ListCreatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg_list).unwrap()).unwrap();
+    assert_eq!(res.creators, vec!["alice".to_string()]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+fn migrate_pools() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+
+    // pools are instantiated with the factory as their wasm admin
+    let msg = ExecuteMsg::CreatePool {
+        title: "first".to_string(),
+        target: Uint128::new(1000),
+        denom: "uatom".to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate2 { admin, .. }) => {
+            assert_eq!(admin.as_deref(), Some(mock_env().contract.address.as_str()))
+        }
+        msg => panic!("unexpected message {:?}", msg),
+    }
+    reply(deps.as_mut(), mock_env(), instantiate_reply("pool1")).unwrap();
+    create_pool(deps.as_mut(), "bob", "second", "pool2");
+    create_pool(deps.as_mut(), "alice", "third", "pool3");
+
+    let migrate = |start_after, limit| ExecuteMsg::MigratePools {
+        new_code_id: 8,
+        msg: Binary::from(b"{}".to_vec()),
+        start_after,
+        limit,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        migrate(None, None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // one page at a time, every migration replies
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        migrate(Some(1), Some(1)),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].id, MIGRATE_REPLY_ID);
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Migrate {
+            contract_addr: "pool2".to_string(),
+            new_code_id: 8,
+            msg: Binary::from(b"{}".to_vec()),
+        })
+    );
+    let result = SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: None,
+    });
+    let id = MIGRATE_REPLY_ID;
+    reply(deps.as_mut(), mock_env(), Reply { id, result }).unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        migrate(Some(2), None),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    // a failed migration is reported without an error so the rest of the batch goes through
+    let result = SubMsgResult::Err("migrate failed".to_string());
+    let res = reply(deps.as_mut(), mock_env(), Reply { id, result }).unwrap();
+    let attr = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+    };
+    assert_eq!(attr("pool_id").unwrap(), "3");
+    assert_eq!(attr("result").unwrap(), "failed");
+    assert_eq!(attr("error").unwrap(), "migrate failed");
+
+    let code_id = |pool_id| {
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Pool { pool_id }).unwrap();
+        from_binary::<PoolInfo>(&res).unwrap().code_id
+    };
+    assert_eq!(code_id(1), 7);
+    assert_eq!(code_id(2), 8);
+    assert_eq!(code_id(3), 7);
+}
+
+#[test]
+fn migrate_checks_version() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::AdminOnly);
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    let version = cw2::get_contract_version(&deps.storage).unwrap();
+    assert_eq!(version.contract, "crates.io:poolFactory");
+
+    cw2::set_contract_version(&mut deps.storage, "crates.io:poolFactory", "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CannotMigrateVersion {
+            previous_version: "99.0.0".to_string()
+        }
+    );
+
+    cw2::set_contract_version(&mut deps.storage, "crates.io:cw20-base", "0.1.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CannotMigrate {
+            previous_contract: "crates.io:cw20-base".to_string()
+        }
+    );
 }
 
-fn handle_transfer_reply(){
-    
+#[test]
+fn migrate_original_config() {
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(|query| {
+        let res = match query {
+            WasmQuery::ContractInfo { contract_addr } => {
+                let mut info = ContractInfoResponse::default();
+                info.code_id = 3;
+                info.creator = "factory".to_string();
+                info.admin = Some(format!("{}-creator", contract_addr));
+                to_binary(&info)
+            }
+            WasmQuery::Raw { contract_addr, .. } => {
+                let cfg = format!(r#"{{"admin":"{}","title":"{}"}}"#, DUMMY, contract_addr);
+                Ok(Binary::from(cfg.into_bytes()))
+            }
+            _ => panic!("unexpected query"),
+        };
+        SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+    });
+
+    cw2::set_contract_version(&mut deps.storage, LEGACY_CONTRACT_NAME, "0.16.0").unwrap();
+    let old = v1::ConfigV1 {
+        admin: Addr::unchecked(DUMMY),
+        pool_code_id: 3,
+    };
+    v1::CONFIG_V1.save(&mut deps.storage, &old).unwrap();
+    for (id, addr) in [(1, "pool1"), (2, "pool2")] {
+        v1::POOLS_V1
+            .save(&mut deps.storage, id, &addr.to_string())
+            .unwrap();
+    }
+    crate::state::POOL_COUNT
+        .save(&mut deps.storage, &2)
+        .unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let cfg: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(cfg.admin, DUMMY);
+    assert_eq!(cfg.pool_code_id, 3);
+    assert_eq!(cfg.creation_policy, CreationPolicy::Permissionless);
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::FeeInfo {}).unwrap();
+    let fees: FeeInfoResponse = from_binary(&res).unwrap();
+    assert_eq!(fees.fee_bps, 0);
+    assert_eq!(fees.fee_collector, DUMMY);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Pool { pool_id: 2 }).unwrap();
+    let pool: PoolInfo = from_binary(&res).unwrap();
+    assert_eq!(
+        pool,
+        PoolInfo {
+            id: 2,
+            addr: Addr::unchecked("pool2"),
+            title: "pool2".to_string(),
+            creator: Addr::unchecked("pool2-creator"),
+            created_at_height: 0,
+            code_id: 3,
+        }
+    );
+    let msg = QueryMsg::PoolByAddress {
+        addr: "pool1".to_string(),
+    };
+    let pool: PoolInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(pool.id, 1);
+
+    // new pools continue the numbering
+    create_pool(deps.as_mut(), "alice", "third", "pool3");
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Pool { pool_id: 3 }).unwrap();
+    let pool: PoolInfo = from_binary(&res).unwrap();
+    assert_eq!(pool.addr, "pool3");
 }
 
+#[test]
+fn guardian_pauses_factory() {
+    let mut deps = mock_dependencies();
+    let msg = InitMsg {
+        guardian: Some("guardian".to_string()),
+        ..init_msg(CreationPolicy::Permissionless)
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("sender", &[]), msg).unwrap();
+
+    // pools are created under the same guardian
+    let msg = ExecuteMsg::CreatePool {
+        title: "first".to_string(),
+        target: Uint128::new(1000),
+        denom: "uatom".to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate2 { msg, .. }) => {
+            let init: InitPoolMsg = from_binary(msg).unwrap();
+            assert_eq!(init.guardian, Some("guardian".to_string()));
+            assert_eq!(init.factory, Some(MOCK_CONTRACT_ADDR.to_string()));
+        }
+        msg => panic!("unexpected message {:?}", msg),
+    }
+    reply(deps.as_mut(), mock_env(), instantiate_reply("pool1")).unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap();
+    assert_eq!(res.attributes[0].value, "pause");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let cfg: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(cfg.guardian, Some("guardian".to_string()));
+    assert!(cfg.paused);
 
-}
\ No newline at end of file
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    let redirect = ExecuteMsg::RedirectFund { pool_id: 1 };
+    let info = mock_info("bob", &coins(100, "uatom"));
+    let err = execute(deps.as_mut(), mock_env(), info, redirect).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    let receive = ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+        sender: "bob".to_string(),
+        amount: Uint128::new(100),
+        msg: to_binary(&ReceiveMsg::RedirectFund { pool_id: 1 }).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("token", &[]), receive).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+
+    // only the admin lifts it
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Unpause {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DUMMY, &[]),
+        ExecuteMsg::Unpause {},
+    )
+    .unwrap();
+    create_pool(deps.as_mut(), "alice", "second", "pool2");
+}
+
+/// MockApi cannot turn 32 byte instantiate2 addresses back into strings, hex encode those instead
+struct Instantiate2Api(MockApi);
+
+impl Api for Instantiate2Api {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        self.0.addr_validate(human)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        self.0.addr_canonicalize(human)
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        match canonical.len() {
+            32 => Ok(Addr::unchecked(
+                HexBinary::from(canonical.as_slice()).to_hex(),
+            )),
+            _ => self.0.addr_humanize(canonical),
+        }
+    }
+
+    fn secp256k1_verify(
+        &self,
+        hash: &[u8],
+        sig: &[u8],
+        key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.0.secp256k1_verify(hash, sig, key)
+    }
+
+    fn secp256k1_recover_pubkey(
+        &self,
+        hash: &[u8],
+        sig: &[u8],
+        param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.0.secp256k1_recover_pubkey(hash, sig, param)
+    }
+
+    fn ed25519_verify(
+        &self,
+        msg: &[u8],
+        sig: &[u8],
+        key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.0.ed25519_verify(msg, sig, key)
+    }
+
+    fn ed25519_batch_verify(
+        &self,
+        msgs: &[&[u8]],
+        sigs: &[&[u8]],
+        keys: &[&[u8]],
+    ) -> Result<bool, VerificationError> {
+        self.0.ed25519_batch_verify(msgs, sigs, keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.0.debug(message)
+    }
+}
+
+#[test]
+fn deterministic_pool_addresses() {
+    let checksum = HexBinary::from(vec![7u8; 32]);
+    let mut querier = MockQuerier::default();
+    let code_info = checksum.clone();
+    querier.update_wasm(move |query| match query {
+        WasmQuery::CodeInfo { code_id: 7 } => {
+            let mut info = CodeInfoResponse::default();
+            info.code_id = 7;
+            info.checksum = code_info.clone();
+            SystemResult::Ok(ContractResult::Ok(to_binary(&info).unwrap()))
+        }
+        _ => panic!("unexpected query"),
+    });
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: Instantiate2Api(MockApi::default()),
+        querier,
+        custom_query_type: Default::default(),
+    };
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+
+    let predict = |deps: &OwnedDeps<_, _, _>, creator: &str, pool_id: u64| {
+        let msg = QueryMsg::PredictPoolAddress {
+            creator: creator.to_string(),
+            salt: Binary::from(pool_id.to_be_bytes()),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_binary::<PredictPoolAddressResponse>(&res)
+            .unwrap()
+            .address
+    };
+    let predicted = predict(&deps, "alice", 1);
+    let factory = deps
+        .api
+        .addr_canonicalize(mock_env().contract.address.as_str())
+        .unwrap();
+    let salt = pool_salt(&Addr::unchecked("alice"), &1u64.to_be_bytes());
+    let expected = instantiate2_address(checksum.as_slice(), &factory, &salt).unwrap();
+    assert_eq!(predicted, HexBinary::from(expected.as_slice()).to_hex());
+
+    // the address depends on both the creator and the pool id
+    assert_ne!(predict(&deps, "bob", 1), predicted);
+    assert_ne!(predict(&deps, "alice", 2), predicted);
+
+    // CreatePool instantiates with the same salt
+    let msg = ExecuteMsg::CreatePool {
+        title: "first".to_string(),
+        target: Uint128::new(1000),
+        denom: "uatom".to_string(),
+        deadline: Expiration::AtHeight(mock_env().block.height + 100),
+        allowed_denoms: vec![],
+        beneficiaries: vec![],
+        milestones: vec![],
+        threshold: None,
+        share_token: None,
+        tokens_per_weight: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate2 {
+            code_id,
+            salt: sent,
+            ..
+        }) => {
+            assert_eq!(*code_id, 7);
+            assert_eq!(sent, &salt);
+        }
+        msg => panic!("unexpected message {:?}", msg),
+    }
+}
+
+#[test]
+fn quadratic_funding_round() {
+    let mut deps = mock_dependencies();
+    setup(deps.as_mut(), CreationPolicy::Permissionless);
+    for (id, addr) in ["pool1", "pool2", "pool3", "pool4"].iter().enumerate() {
+        create_pool(deps.as_mut(), "alice", &format!("pool {}", id + 1), addr);
+    }
+
+    let height = mock_env().block.height;
+    let round = |pool_ids: Vec<u64>| ExecuteMsg::CreateRound {
+        start: Expiration::AtHeight(height),
+        end: Expiration::AtHeight(height + 10),
+        pool_ids,
+    };
+
+    // the pot is a single native coin, pools must exist and be listed once
+    let info = mock_info("sponsor", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, round(vec![1])).unwrap_err();
+    assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+    let info = mock_info("sponsor", &coins(1000, "uatom"));
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), round(vec![1, 1])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRound {});
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), round(vec![])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRound {});
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), round(vec![1, 9])).unwrap_err();
+    assert_eq!(err, ContractError::PoolNotFound { pool_id: 9 });
+    let msg = ExecuteMsg::CreateRound {
+        start: Expiration::AtHeight(height),
+        end: Expiration::AtTime(mock_env().block.time.plus_seconds(60)),
+        pool_ids: vec![1],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRound {});
+    let msg = ExecuteMsg::CreateRound {
+        start: Expiration::AtHeight(height),
+        end: Expiration::Never {},
+        pool_ids: vec![1],
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRound {});
+
+    let res = execute(deps.as_mut(), mock_env(), info, round(vec![1, 2, 3])).unwrap();
+    assert_eq!(res.attributes[1], ("round_id", "1"));
+
+    // many small contributors beat one large one
+    for contributor in ["bob", "carol", "dave", "erin"] {
+        redirect(deps.as_mut(), contributor, 1, &coins(25, "uatom"));
+    }
+    redirect(deps.as_mut(), "bob", 2, &coins(36, "uatom"));
+    redirect(deps.as_mut(), "bob", 2, &coins(64, "uatom"));
+    redirect(deps.as_mut(), "carol", 3, &coins(9, "uatom"));
+    redirect(
+        deps.as_mut(),
+        "dave",
+        3,
+        &[coin(16, "uatom"), coin(500, "ujuno")],
+    );
+    // not part of the round
+    redirect(deps.as_mut(), "erin", 4, &coins(100, "uatom"));
+
+    // pool 1: 20² - 100 = 300, pool 2: 10² - 100 = 0, pool 3: 7² - 25 = 24
+    let expected = vec![
+        RoundMatch {
+            pool_id: 1,
+            contributors: 4,
+            total: Uint128::new(100),
+            matched: Uint128::new(925),
+        },
+        RoundMatch {
+            pool_id: 2,
+            contributors: 1,
+            total: Uint128::new(100),
+            matched: Uint128::zero(),
+        },
+        RoundMatch {
+            pool_id: 3,
+            contributors: 2,
+            total: Uint128::new(25),
+            matched: Uint128::new(74),
+        },
+    ];
+    let msg = QueryMsg::RoundMatches { round_id: 1 };
+    let res: RoundMatchesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+    assert_eq!(res.denom, "uatom");
+    assert_eq!(res.matches, expected);
+
+    let close = ExecuteMsg::CloseRound { round_id: 1 };
+    let info = mock_info("anyone", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), close.clone()).unwrap_err();
+    assert_eq!(err, ContractError::RoundNotEnded {});
+
+    // contributions after the end are not matched
+    let mut env = mock_env();
+    env.block.height += 10;
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("bob", &coins(100, "uatom")),
+        ExecuteMsg::RedirectFund { pool_id: 3 },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let result = SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: None,
+    });
+    let id = REDIRECT_FUNDS_ID;
+    reply(deps.as_mut(), env.clone(), Reply { id, result }).unwrap();
+    let res: RoundMatchesResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(res.matches, expected);
+
+    // pool 1 succeeded and pool 3 failed in the meantime
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, .. } => {
+            let status = match contract_addr.as_str() {
+                "pool3" => PoolStatus::Failed,
+                _ => PoolStatus::Succeeded,
+            };
+            let res = to_binary(&StatusResponse { status });
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        }
+        _ => panic!("unexpected query"),
+    });
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), close.clone()).unwrap();
+    let sends: Vec<_> = res.messages.into_iter().map(|m| m.msg).collect();
+    assert_eq!(
+        sends,
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "pool1".to_string(),
+                amount: coins(925, "uatom"),
+            }),
+            // its match goes back along with the rounding dust
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "sponsor".to_string(),
+                amount: coins(75, "uatom"),
+            }),
+        ]
+    );
+
+    let err = execute(deps.as_mut(), env.clone(), info, close).unwrap_err();
+    assert_eq!(err, ContractError::RoundClosed {});
+    let res: RoundResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::Round { round_id: 1 }).unwrap()).unwrap();
+    assert_eq!(res.pot, coin(1000, "uatom"));
+    assert_eq!(res.pool_ids, vec![1, 2, 3]);
+    assert!(res.closed);
+}
@@ -0,0 +1,91 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_binary, Api, BankMsg, Coin, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cw20::{Cw20Coin, Cw20ExecuteMsg};
+
+/// Same abstraction as `cw20-ics20::amount`: cw20 tokens are keyed as `cw20:<contract>`
+/// wherever we store a denom, native coins by their bank denom.
+#[cw_serde]
+pub enum Amount {
+    Native(Coin),
+    Cw20(Cw20Coin),
+}
+
+impl Amount {
+    pub fn from_parts(denom: String, amount: Uint128) -> Self {
+        match denom.strip_prefix("cw20:") {
+            Some(address) => Amount::Cw20(Cw20Coin {
+                address: address.into(),
+                amount,
+            }),
+            None => Amount::Native(Coin { denom, amount }),
+        }
+    }
+
+    pub fn cw20(amount: u128, addr: &str) -> Self {
+        Amount::Cw20(Cw20Coin {
+            address: addr.into(),
+            amount: Uint128::new(amount),
+        })
+    }
+
+    pub fn native(amount: u128, denom: &str) -> Self {
+        Amount::Native(Coin {
+            denom: denom.to_string(),
+            amount: Uint128::new(amount),
+        })
+    }
+}
+
+/// Makes sure the contract part of a `cw20:<contract>` denom is a valid address
+pub fn validate_denom(api: &dyn Api, denom: &str) -> StdResult<()> {
+    if let Some(address) = denom.strip_prefix("cw20:") {
+        api.addr_validate(address)?;
+    }
+    Ok(())
+}
+
+impl Amount {
+    pub fn denom(&self) -> String {
+        match self {
+            Amount::Native(c) => c.denom.clone(),
+            Amount::Cw20(c) => format!("cw20:{}", c.address.as_str()),
+        }
+    }
+
+    pub fn amount(&self) -> Uint128 {
+        match self {
+            Amount::Native(c) => c.amount,
+            Amount::Cw20(c) => c.amount,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amount().is_zero()
+    }
+
+    /// Message sending this amount to `recipient`, either through the bank or the cw20 contract
+    pub fn into_msg(self, recipient: String) -> StdResult<CosmosMsg> {
+        Ok(match self {
+            Amount::Native(coin) => BankMsg::Send {
+                to_address: recipient,
+                amount: vec![coin],
+            }
+            .into(),
+            Amount::Cw20(coin) => WasmMsg::Execute {
+                contract_addr: coin.address,
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient,
+                    amount: coin.amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        })
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.amount(), self.denom())
+    }
+}
@@ -0,0 +1,325 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Binary, Coin, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw_utils::{Expiration, Threshold};
+
+#[cw_serde]
+pub struct InitMsg {
+    pub admin: String,
+    pub pool_code_id: u64,
+    /// cw721 contract the factory mints a receipt on for every redirected coin, the factory must be its minter
+    pub receipt_nft: Option<String>,
+    /// cut of every redirected contribution, in basis points
+    pub fee_bps: u16,
+    /// where collected fees go, defaults to `admin`
+    pub fee_collector: Option<String>,
+    /// who can create pools
+    pub creation_policy: CreationPolicy,
+    /// can pause the factory and every pool it creates in an emergency
+    pub guardian: Option<String>,
+}
+
+/// Storage key of the factory's pool address to pool id map, see `helpers::factory_pool_id`
+pub const POOL_IDS_KEY: &str = "pool_ids";
+
+// the pool messages themselves, so what the factory sends cannot drift from what pools accept
+pub use crate::pool::{Beneficiary, InitMsg as InitPoolMsg, MilestoneMsg, ShareTokenMsg};
+
+/// The part of the cw721-base execute interface the factory uses to mint receipts
+#[cw_serde]
+pub enum Cw721ExecuteMsg {
+    Mint(ReceiptMintMsg),
+}
+
+#[cw_serde]
+pub struct ReceiptMintMsg {
+    pub token_id: String,
+    pub owner: String,
+    pub token_uri: Option<String>,
+    pub extension: ReceiptMetadata,
+}
+
+/// Stored as extension on every receipt NFT
+#[cw_serde]
+pub struct ReceiptMetadata {
+    pub pool_id: u64,
+    pub amount: Uint128,
+    pub denom: String,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+// only deserialized once per call, not worth boxing `CreatePool` for
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
+pub enum ExecuteMsg {
+    CreatePool {
+        title: String,
+        target: Uint128,
+        denom: String,
+        deadline: Expiration,
+        /// other native or `cw20:<contract>` denoms the pool accepts besides `denom`
+        allowed_denoms: Vec<String>,
+        /// who the pool splits its withdrawals between, empty for a single recipient
+        beneficiaries: Vec<Beneficiary>,
+        /// tranches released once contributors approve them, empty to withdraw right away
+        milestones: Vec<MilestoneMsg>,
+        /// how contributors approve milestones, required with milestones
+        threshold: Option<Threshold>,
+        /// cw20 the pool mints its contributors shares on, none if omitted
+        share_token: Option<ShareTokenMsg>,
+        /// target denom contributed per unit of cw4 weight in the pool, 1 if omitted
+        tokens_per_weight: Option<Uint128>,
+    },
+    RedirectFund {
+        pool_id: u64,
+    },
+    /// Admin only: send the accumulated fees to the fee collector
+    CollectFees {},
+    /// Admin only: change the given settings, the rest stays as is. An empty `receipt_nft` or
+    /// `guardian` unsets it. Pools created before keep the code id they were instantiated with.
+    UpdateConfig {
+        pool_code_id: Option<u64>,
+        receipt_nft: Option<String>,
+        fee_bps: Option<u16>,
+        fee_collector: Option<String>,
+        creation_policy: Option<CreationPolicy>,
+        guardian: Option<String>,
+    },
+    /// Admin only: propose a new admin, who has to `AcceptAdmin` to take over. Pools created
+    /// by the factory answer to its admin and guardian, so they change hands along with it.
+    TransferAdmin {
+        new_admin: String,
+    },
+    /// Pending admin only: complete the transfer started by `TransferAdmin`
+    AcceptAdmin {},
+    /// Admin only: manage who can create pools under `CreationPolicy::Allowlist`
+    UpdateCreators {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Admin only: migrate a page of pools (ordered by id) to `new_code_id`, sending `msg` as
+    /// their migrate message. A pool failing to migrate is reported without aborting the batch.
+    MigratePools {
+        new_code_id: u64,
+        msg: Binary,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Guardian only: block `CreatePool` and `RedirectFund`
+    Pause {},
+    /// Admin only: lift a pause
+    Unpause {},
+    /// Sponsor a quadratic funding round with the single native coin sent as the matching pot.
+    /// `RedirectFund` contributions in its denom to the given pools between `start` and `end`
+    /// are tallied per contributor. The round has to end at some point.
+    CreateRound {
+        start: Expiration,
+        end: Expiration,
+        pool_ids: Vec<u64>,
+    },
+    /// Anyone, once the round ended: pay the matching pot out to the pools of the round, as a
+    /// contribution of the sponsor to those still open. What cannot be matched goes back to the
+    /// sponsor, along with the share of pools that are paused, failed or were closed meanwhile.
+    CloseRound {
+        round_id: u64,
+    },
+    /// Redirect cw20 tokens, the embedded message must be a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+}
+
+/// Embedded in the `Cw20ReceiveMsg` of a cw20 `Send` to the factory
+#[cw_serde]
+pub enum ReceiveMsg {
+    RedirectFund { pool_id: u64 },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(PoolResponse)]
+    PoolAddress {
+        pool_id: u64,
+    },
+    #[returns(ConfigResponse)]
+    Config {},
+    /// Full registry entry of a single pool
+    #[returns(PoolInfo)]
+    Pool {
+        pool_id: u64,
+    },
+    /// Registry entry of the pool at the given address, errors if it was not created by this factory
+    #[returns(PoolInfo)]
+    PoolByAddress {
+        addr: String,
+    },
+    /// All pools ordered by id
+    #[returns(ListPoolsResponse)]
+    ListPools {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Everything the given address redirected through the factory, summed over all pools
+    #[returns(ContributorTotalsResponse)]
+    ContributorTotals {
+        contributor: String,
+    },
+    /// Everything redirected to the given pool through the factory
+    #[returns(PoolTotalsResponse)]
+    PoolTotals {
+        pool_id: u64,
+    },
+    /// Pools created by the given address, ordered by id
+    #[returns(ListPoolsResponse)]
+    PoolsByCreator {
+        creator: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The fee taken from redirected contributions and who collects it
+    #[returns(FeeInfoResponse)]
+    FeeInfo {},
+    /// Fees accumulated and not collected yet, per denom
+    #[returns(CollectedFeesResponse)]
+    CollectedFees {},
+    /// Admin proposed by `TransferAdmin` that did not accept yet
+    #[returns(PendingAdminResponse)]
+    PendingAdmin {},
+    /// Addresses allowed to create pools under `CreationPolicy::Allowlist`
+    #[returns(ListCreatorsResponse)]
+    ListCreators {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Address a pool instantiated from the current pool code gets for `creator` and `salt`,
+    /// before it exists. Pools made by `CreatePool` use their id as 8 big-endian bytes for `salt`.
+    #[returns(PredictPoolAddressResponse)]
+    PredictPoolAddress {
+        creator: String,
+        salt: Binary,
+    },
+    /// A quadratic funding round and its pot
+    #[returns(RoundResponse)]
+    Round {
+        round_id: u64,
+    },
+    /// What every pool of the round gets from the pot, projected from the contributions so far
+    /// until the round is closed
+    #[returns(RoundMatchesResponse)]
+    RoundMatches {
+        round_id: u64,
+    },
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum CreationPolicy {
+    /// only the factory admin
+    AdminOnly,
+    /// the admin and the addresses in `CREATORS`
+    Allowlist,
+    /// anyone
+    Permissionless,
+}
+
+#[cw_serde]
+pub struct PoolInfo {
+    pub id: u64,
+    pub addr: Addr,
+    pub title: String,
+    pub creator: Addr,
+    pub created_at_height: u64,
+    pub code_id: u64,
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub admin: String,
+    pub pool_code_id: u64,
+    pub receipt_nft: Option<String>,
+    pub creation_policy: CreationPolicy,
+    pub guardian: Option<String>,
+    pub paused: bool,
+}
+
+#[cw_serde]
+pub struct PendingAdminResponse {
+    pub pending_admin: Option<String>,
+}
+
+#[cw_serde]
+pub struct ListCreatorsResponse {
+    pub creators: Vec<String>,
+}
+
+#[cw_serde]
+pub struct PredictPoolAddressResponse {
+    pub address: String,
+}
+
+#[cw_serde]
+pub struct FeeInfoResponse {
+    pub fee_bps: u16,
+    pub fee_collector: String,
+}
+
+#[cw_serde]
+pub struct CollectedFeesResponse {
+    pub fees: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PoolResponse {
+    pub pool_id: u64,
+    pub pool_addr: String,
+}
+
+#[cw_serde]
+pub struct ListPoolsResponse {
+    pub pools: Vec<PoolInfo>,
+}
+
+#[cw_serde]
+pub struct ContributorTotalsResponse {
+    pub contributor: String,
+    /// everything redirected by this contributor over all pools, one coin per denom
+    pub totals: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PoolTotalsResponse {
+    pub pool_id: u64,
+    /// everything redirected to this pool through the factory, one coin per denom
+    pub totals: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct RoundResponse {
+    pub id: u64,
+    pub sponsor: String,
+    pub pot: Coin,
+    pub start: Expiration,
+    pub end: Expiration,
+    pub pool_ids: Vec<u64>,
+    pub closed: bool,
+}
+
+#[cw_serde]
+pub struct RoundMatchesResponse {
+    pub round_id: u64,
+    pub denom: String,
+    /// one entry per pool of the round, in the order they were given
+    pub matches: Vec<RoundMatch>,
+}
+
+#[cw_serde]
+pub struct RoundMatch {
+    pub pool_id: u64,
+    /// unique contributors during the round
+    pub contributors: u64,
+    /// contributed in the round denom during the round
+    pub total: Uint128,
+    /// share of the pot
+    pub matched: Uint128,
+}
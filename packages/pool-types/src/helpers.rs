@@ -0,0 +1,340 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Coin, CosmosMsg, CustomQuery, QuerierWrapper, QueryRequest, StdResult,
+    Uint128, WasmMsg, WasmQuery,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::Map;
+use cw_utils::{Expiration, Threshold};
+
+use crate::factory::{
+    Beneficiary, ConfigResponse as FactoryConfigResponse, ContributorTotalsResponse,
+    ExecuteMsg as FactoryExecuteMsg, MilestoneMsg, PoolInfo, PoolResponse, PoolTotalsResponse,
+    PredictPoolAddressResponse, QueryMsg as FactoryQueryMsg, ReceiveMsg as FactoryReceiveMsg,
+    ShareTokenMsg, POOL_IDS_KEY,
+};
+use crate::pool::{
+    ConfigResponse, ContributionResponse, ExecuteMsg, PoolStatus, QueryMsg, ReceiveMsg,
+    StatusResponse, VestingMsg,
+};
+
+/// PoolContract is a wrapper around Addr that provides helpers for working with a pool,
+/// like `cw4::Cw4Contract` does for groups
+#[cw_serde]
+pub struct PoolContract(pub Addr);
+
+impl PoolContract {
+    pub fn new(addr: Addr) -> Self {
+        PoolContract(addr)
+    }
+
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// Any execute message, with `funds` attached
+    pub fn call(&self, msg: ExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+            funds,
+        }
+        .into())
+    }
+
+    /// Contribute the native coin sent along
+    pub fn contribute(&self, funds: Coin) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Contribute { on_behalf_of: None };
+        self.call(msg, vec![funds])
+    }
+
+    /// Contribute `amount` of the cw20 `token`, sent from the caller's balance
+    pub fn contribute_cw20<T: Into<String>>(
+        &self,
+        token: T,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ReceiveMsg::Contribute { on_behalf_of: None };
+        self.send_cw20(token, amount, msg)
+    }
+
+    /// Redeem `amount` of the pool shares, `token` being the share token of the pool
+    pub fn redeem<T: Into<String>>(&self, token: T, amount: Uint128) -> StdResult<CosmosMsg> {
+        self.send_cw20(token, amount, ReceiveMsg::Redeem {})
+    }
+
+    fn send_cw20<T: Into<String>>(
+        &self,
+        token: T,
+        amount: Uint128,
+        msg: ReceiveMsg,
+    ) -> StdResult<CosmosMsg> {
+        let send = Cw20ExecuteMsg::Send {
+            contract: self.addr().into(),
+            amount,
+            msg: to_binary(&msg)?,
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: token.into(),
+            msg: to_binary(&send)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    /// Admin only, see `ExecuteMsg::WithdrawFunds`
+    pub fn withdraw<T: Into<String>>(
+        &self,
+        recipient: Option<T>,
+        amount: Option<Vec<Coin>>,
+        vesting: Option<VestingMsg>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::WithdrawFunds {
+            recipient: recipient.map(Into::into),
+            amount,
+            vesting,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn refund(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Refund {}, vec![])
+    }
+
+    pub fn add_contribution_hook<T: Into<String>>(&self, addr: T) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::AddContributionHook { addr: addr.into() };
+        self.call(msg, vec![])
+    }
+
+    pub fn remove_contribution_hook<T: Into<String>>(&self, addr: T) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RemoveContributionHook { addr: addr.into() };
+        self.call(msg, vec![])
+    }
+
+    fn encode_smart_query<Q: CustomQuery>(&self, msg: QueryMsg) -> StdResult<QueryRequest<Q>> {
+        Ok(WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into())
+    }
+
+    pub fn config<Q: CustomQuery>(&self, querier: &QuerierWrapper<Q>) -> StdResult<ConfigResponse> {
+        let query = self.encode_smart_query(QueryMsg::Config {})?;
+        querier.query(&query)
+    }
+
+    /// Current lifecycle state, taking the deadline into account
+    pub fn status<Q: CustomQuery>(&self, querier: &QuerierWrapper<Q>) -> StdResult<PoolStatus> {
+        let query = self.encode_smart_query(QueryMsg::Status {})?;
+        let res: StatusResponse = querier.query(&query)?;
+        Ok(res.status)
+    }
+
+    /// Everything `address` contributed, one coin per denom
+    pub fn contribution<Q: CustomQuery, T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        address: T,
+    ) -> StdResult<Vec<Coin>> {
+        let query = self.encode_smart_query(QueryMsg::Contribution {
+            address: address.into(),
+        })?;
+        let res: ContributionResponse = querier.query(&query)?;
+        Ok(res.contributions)
+    }
+
+    /// Contracts notified of contributions and withdrawals
+    pub fn contribution_hooks<Q: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+    ) -> StdResult<Vec<String>> {
+        let query = self.encode_smart_query(QueryMsg::ContributionHooks {})?;
+        let res: cw_controllers::HooksResponse = querier.query(&query)?;
+        Ok(res.hooks)
+    }
+}
+
+/// Returns the id under which `pool` is registered in the `factory` contract, or `None` if the
+/// factory did not create it. This is a raw storage query, so a pool can use it to check where it
+/// (or one of its callers) comes from without depending on the factory's query messages.
+pub fn factory_pool_id(
+    querier: &QuerierWrapper,
+    factory: Addr,
+    pool: &Addr,
+) -> StdResult<Option<u64>> {
+    let pool_ids: Map<&Addr, u64> = Map::new(POOL_IDS_KEY);
+    pool_ids.query(querier, factory, pool)
+}
+
+/// PoolFactoryContract is a wrapper around Addr that provides helpers for working with the
+/// factory, like `cw4::Cw4Contract` does for groups
+#[cw_serde]
+pub struct PoolFactoryContract(pub Addr);
+
+impl PoolFactoryContract {
+    pub fn new(addr: Addr) -> Self {
+        PoolFactoryContract(addr)
+    }
+
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// Any execute message, with `funds` attached
+    pub fn call(&self, msg: FactoryExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+            funds,
+        }
+        .into())
+    }
+
+    /// See `factory::ExecuteMsg::CreatePool`
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_pool(
+        &self,
+        title: String,
+        target: Uint128,
+        denom: String,
+        deadline: Expiration,
+        allowed_denoms: Vec<String>,
+        beneficiaries: Vec<Beneficiary>,
+        milestones: Vec<MilestoneMsg>,
+        threshold: Option<Threshold>,
+        share_token: Option<ShareTokenMsg>,
+        tokens_per_weight: Option<Uint128>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = FactoryExecuteMsg::CreatePool {
+            title,
+            target,
+            denom,
+            deadline,
+            allowed_denoms,
+            beneficiaries,
+            milestones,
+            threshold,
+            share_token,
+            tokens_per_weight,
+        };
+        self.call(msg, vec![])
+    }
+
+    /// Forward the native coins sent along to the pool, less the factory fee
+    pub fn redirect_fund(&self, pool_id: u64, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        self.call(FactoryExecuteMsg::RedirectFund { pool_id }, funds)
+    }
+
+    /// Forward `amount` of the cw20 `token` to the pool, sent from the caller's balance
+    pub fn redirect_cw20<T: Into<String>>(
+        &self,
+        token: T,
+        pool_id: u64,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let send = Cw20ExecuteMsg::Send {
+            contract: self.addr().into(),
+            amount,
+            msg: to_binary(&FactoryReceiveMsg::RedirectFund { pool_id })?,
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: token.into(),
+            msg: to_binary(&send)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    fn encode_smart_query<Q: CustomQuery>(
+        &self,
+        msg: FactoryQueryMsg,
+    ) -> StdResult<QueryRequest<Q>> {
+        Ok(WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into())
+    }
+
+    pub fn config<Q: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+    ) -> StdResult<FactoryConfigResponse> {
+        let query = self.encode_smart_query(FactoryQueryMsg::Config {})?;
+        querier.query(&query)
+    }
+
+    pub fn pool_address<Q: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        pool_id: u64,
+    ) -> StdResult<Addr> {
+        let query = self.encode_smart_query(FactoryQueryMsg::PoolAddress { pool_id })?;
+        let res: PoolResponse = querier.query(&query)?;
+        Ok(Addr::unchecked(res.pool_addr))
+    }
+
+    /// The pool with the given id, ready to be called
+    pub fn pool_contract<Q: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        pool_id: u64,
+    ) -> StdResult<PoolContract> {
+        self.pool_address(querier, pool_id).map(PoolContract::new)
+    }
+
+    /// Full registry entry of a pool
+    pub fn pool<Q: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        pool_id: u64,
+    ) -> StdResult<PoolInfo> {
+        let query = self.encode_smart_query(FactoryQueryMsg::Pool { pool_id })?;
+        querier.query(&query)
+    }
+
+    /// Id of `pool` if this factory created it, see `factory_pool_id`
+    pub fn pool_id(&self, querier: &QuerierWrapper, pool: &Addr) -> StdResult<Option<u64>> {
+        factory_pool_id(querier, self.addr(), pool)
+    }
+
+    /// Everything `contributor` redirected through the factory, one coin per denom
+    pub fn contributor_totals<Q: CustomQuery, T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        contributor: T,
+    ) -> StdResult<Vec<Coin>> {
+        let query = self.encode_smart_query(FactoryQueryMsg::ContributorTotals {
+            contributor: contributor.into(),
+        })?;
+        let res: ContributorTotalsResponse = querier.query(&query)?;
+        Ok(res.totals)
+    }
+
+    /// Everything redirected to the pool through the factory, one coin per denom
+    pub fn pool_totals<Q: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        pool_id: u64,
+    ) -> StdResult<Vec<Coin>> {
+        let query = self.encode_smart_query(FactoryQueryMsg::PoolTotals { pool_id })?;
+        let res: PoolTotalsResponse = querier.query(&query)?;
+        Ok(res.totals)
+    }
+
+    /// Where a pool created by `creator` with `salt` will be instantiated
+    pub fn predict_pool_address<Q: CustomQuery, T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper<Q>,
+        creator: T,
+        salt: Binary,
+    ) -> StdResult<Addr> {
+        let query = self.encode_smart_query(FactoryQueryMsg::PredictPoolAddress {
+            creator: creator.into(),
+            salt,
+        })?;
+        let res: PredictPoolAddressResponse = querier.query(&query)?;
+        Ok(Addr::unchecked(res.address))
+    }
+}
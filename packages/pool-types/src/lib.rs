@@ -0,0 +1,12 @@
+/*!
+Messages and responses of the pool and pool factory contracts, along with the `Amount` they
+both move funds with and typed helpers to call them.
+
+Both contracts re-export these from their `msg` modules, and clients should depend on this
+crate rather than on the contracts, so a change to either side breaks the build of the other.
+*/
+
+pub mod amount;
+pub mod factory;
+pub mod helpers;
+pub mod pool;
@@ -0,0 +1,410 @@
+use std::fmt;
+
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{to_binary, Binary, Coin, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cw20::{Cw20Coin, Cw20ReceiveMsg, MinterResponse};
+use cw_utils::{Expiration, Threshold};
+
+#[cw_serde]
+pub struct InitMsg {
+    /// ignored if the pool has a `factory`, its admin is the factory admin
+    pub admin: String,
+    pub title: String,
+    /// amount of `denom` that has to be raised before the admin can withdraw
+    pub target: Uint128,
+    /// denom the target is counted in, cw20 tokens are given as `cw20:<contract>`
+    pub denom: String,
+    /// if `target` is not met by then, contributors can reclaim their funds
+    pub deadline: Expiration,
+    /// other denoms accepted as contribution, they do not count towards `target`
+    pub allowed_denoms: Vec<String>,
+    /// withdrawals are split between these, leave empty to withdraw to a single recipient
+    pub beneficiaries: Vec<Beneficiary>,
+    /// tranches of `target` released once contributors approve them, the admin can only withdraw
+    /// the rest when all are released. Leave empty to withdraw right away.
+    pub milestones: Vec<MilestoneMsg>,
+    /// how contributors approve milestones, weighted by what they contributed in `denom`
+    pub threshold: Option<Threshold>,
+    /// can pause contributions in an emergency, only the admin can resume them. Ignored if the
+    /// pool has a `factory`, like `admin`.
+    pub guardian: Option<String>,
+    /// mint contributors a cw20 share of the pool for what they put in towards `target`
+    pub share_token: Option<ShareTokenMsg>,
+    /// factory creating the pool, which forwards contributions on behalf of its users.
+    /// Must be the sender.
+    pub factory: Option<String>,
+    /// target denom contributed per unit of cw4 weight, 1 if omitted. Raise it for denoms with
+    /// many decimals, weights are capped at `u64::MAX`.
+    pub tokens_per_weight: Option<Uint128>,
+}
+
+/// Shares are minted 1:1 for target denom contributions. Once the pool is closed, holders can
+/// redeem them for a pro rata slice of what it still holds. Refunds of a failed pool go by
+/// contribution, not by shares.
+#[cw_serde]
+pub struct ShareTokenMsg {
+    /// cw20-base code the pool instantiates the token from, as its only minter
+    pub code_id: u64,
+    pub name: String,
+    pub symbol: String,
+    /// should match the target denom
+    pub decimals: u8,
+}
+
+/// The part of the cw20-base instantiate message the pool sets for its share token
+#[cw_serde]
+pub struct Cw20InstantiateMsg {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub initial_balances: Vec<Cw20Coin>,
+    pub mint: Option<MinterResponse>,
+}
+
+#[cw_serde]
+pub struct MilestoneMsg {
+    pub description: String,
+    /// amount of `denom` released, all milestones must add up to the target
+    pub amount: Uint128,
+    /// voting closes at this point, after the pool deadline and in the same unit
+    pub deadline: Expiration,
+}
+
+/// Streams a payout linearly instead of sending it. `start`, `end` and the cliff must all be
+/// heights or all be times.
+#[cw_serde]
+pub struct VestingMsg {
+    pub start: Expiration,
+    pub end: Expiration,
+    /// nothing can be claimed before this, what vested until then becomes claimable at once
+    pub cliff: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct Beneficiary {
+    pub address: String,
+    /// share of every withdrawal in basis points, all shares must add up to 10000
+    pub share: u16,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    /// Only used when migrating a pool created before funding targets existed
+    pub funding: Option<Funding>,
+}
+
+/// What the pool already holds in `denom` counts as raised, contributed by its admin
+#[cw_serde]
+pub struct Funding {
+    pub target: Uint128,
+    pub denom: String,
+    pub deadline: Expiration,
+    /// factory that created the pool, so it can keep redirecting funds to it
+    pub factory: Option<String>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Admin only: send `amount` (native or `cw20:<contract>` denoms), everything the pool
+    /// holds if omitted. It is split between the beneficiaries if the pool has any,
+    /// otherwise `recipient` gets all of it. With `vesting` every payee gets a stream to claim
+    /// from instead.
+    WithdrawFunds {
+        recipient: Option<String>,
+        amount: Option<Vec<Coin>>,
+        vesting: Option<VestingMsg>,
+    },
+    /// Send the sender everything vested so far in their streams
+    ClaimVested {},
+    /// Admin only: stop the streams of `recipient`, what did not vest yet stays in the pool
+    CancelVesting { recipient: String },
+    /// Record the allowed denom sent along with this message against the sender, or against
+    /// `on_behalf_of` if the pool factory forwards it
+    Contribute { on_behalf_of: Option<String> },
+    /// Contribute cw20 tokens, the embedded message must be a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+    /// Give the sender back everything they contributed, once the deadline passed without meeting the target
+    Refund {},
+    /// Admin only: fail an open pool early so contributors can get a refund
+    Cancel {},
+    /// Admin only: close a succeeded pool once the funds have been withdrawn. Share holders can
+    /// redeem what is left from then on. Milestone pools can only close once all are released.
+    Close {},
+    /// Admin only: accept contributions in another native or `cw20:<contract>` denom
+    AddAllowedDenom { denom: String },
+    /// Admin only: stop accepting a denom. Balances are only withdrawn for allowed denoms,
+    /// so withdraw any cw20 tokens held before removing them.
+    RemoveAllowedDenom { denom: String },
+    /// Admin only: replace the beneficiaries future withdrawals are split between
+    UpdateBeneficiaries { beneficiaries: Vec<Beneficiary> },
+    /// Contributors only: approve or reject a milestone, weighted by their target denom contribution
+    VoteMilestone { milestone_id: u64, approve: bool },
+    /// Admin only: pay out the tranche of a passed milestone, split like `WithdrawFunds`
+    ReleaseMilestone {
+        milestone_id: u64,
+        recipient: Option<String>,
+    },
+    /// Admin only: notify `addr` of member weight changes (cw4)
+    AddHook { addr: String },
+    /// Admin only (cw4)
+    RemoveHook { addr: String },
+    /// Admin only: send `addr` a `ContributionHookMsg` whenever funds enter or leave the pool
+    AddContributionHook { addr: String },
+    /// Admin only
+    RemoveContributionHook { addr: String },
+    /// Guardian only: stop accepting contributions, refunds keep working
+    Pause {},
+    /// Admin only: accept contributions again
+    Unpause {},
+}
+
+/// Sent to every contribution hook, once per payee and denom
+#[cw_serde]
+pub struct ContributionHookMsg {
+    pub change: ContributionChange,
+    /// who contributed, or who the funds were paid out to
+    pub contributor: String,
+    pub amount: Uint128,
+    /// native or `cw20:<contract>`
+    pub denom: String,
+    /// what the pool holds of `denom` after the change, less what is still vesting
+    pub pool_total: Uint128,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum ContributionChange {
+    Contributed,
+    /// a contributor got their funds back from a failed pool
+    Refunded,
+    /// the admin withdrew or released a milestone, streams count when they are created
+    Withdrawn,
+    /// a share holder redeemed shares of a closed pool
+    Redeemed,
+}
+
+impl ContributionHookMsg {
+    /// serializes the message
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = ContributionHookExecuteMsg::ContributionHook(self);
+        to_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+/// Hook contracts must accept this in their execute message
+#[cw_serde]
+pub enum ContributionHookExecuteMsg {
+    ContributionHook(ContributionHookMsg),
+}
+
+/// Embedded in the `Cw20ReceiveMsg` of a cw20 `Send` to this pool
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// `on_behalf_of` is only accepted from the pool factory, like `ExecuteMsg::Contribute`
+    Contribute { on_behalf_of: Option<String> },
+    /// Share holders only, once the pool is closed: burn the shares sent for the same fraction
+    /// of every balance the pool holds, less what is still vesting
+    Redeem {},
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(ConfigResponse)]
+    Config {},
+    /// Current lifecycle state of the pool
+    #[returns(StatusResponse)]
+    Status {},
+    /// Everything the given address contributed so far, one coin per denom
+    #[returns(ContributionResponse)]
+    Contribution { address: String },
+    /// Paginated list of contributors, `start_after` being the last address of the previous page
+    #[returns(ListContributionsResponse)]
+    ListContributions {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Every denom accepted as contribution, including the target denom
+    #[returns(ListAllowedDenomsResponse)]
+    ListAllowedDenoms {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Total withdrawn by the admin so far, per denom
+    #[returns(WithdrawnResponse)]
+    Withdrawn {},
+    #[returns(BeneficiariesResponse)]
+    Beneficiaries {},
+    #[returns(MilestonesResponse)]
+    Milestones {},
+    /// cw4: the pool admin
+    #[returns(cw4::AdminResponse)]
+    Admin {},
+    /// cw4: total target denom contributed, as of the beginning of `at_height` if given
+    #[returns(cw4::TotalWeightResponse)]
+    TotalWeight { at_height: Option<u64> },
+    /// cw4: contributors of the target denom
+    #[returns(cw4::MemberListResponse)]
+    ListMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// cw4: weight of a contributor, as of the beginning of `at_height` if given
+    #[returns(cw4::MemberResponse)]
+    Member {
+        addr: String,
+        at_height: Option<u64>,
+    },
+    /// cw4: contracts notified of member changes
+    #[returns(cw_controllers::HooksResponse)]
+    Hooks {},
+    /// Contracts notified of contributions and withdrawals
+    #[returns(cw_controllers::HooksResponse)]
+    ContributionHooks {},
+    /// Streams paid out to `recipient` and how far they vested
+    #[returns(VestingStatusResponse)]
+    VestingStatus { recipient: String },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub admin: String,
+    pub title: String,
+    pub target: Uint128,
+    pub denom: String,
+    pub deadline: Expiration,
+    /// total amount of `denom` contributed so far
+    pub raised: Uint128,
+    pub guardian: Option<String>,
+    pub paused: bool,
+    /// cw20 contract of the pool shares, if the pool has any
+    pub share_token: Option<String>,
+    /// factory that created the pool, if any
+    pub factory: Option<String>,
+}
+
+#[cw_serde]
+pub struct StatusResponse {
+    pub status: PoolStatus,
+}
+
+#[cw_serde]
+pub struct ContributionResponse {
+    pub address: String,
+    pub contributions: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct ListContributionsResponse {
+    pub contributions: Vec<ContributionResponse>,
+}
+
+#[cw_serde]
+pub struct ListAllowedDenomsResponse {
+    pub denoms: Vec<String>,
+}
+
+#[cw_serde]
+pub struct WithdrawnResponse {
+    pub withdrawn: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct BeneficiariesResponse {
+    pub beneficiaries: Vec<Beneficiary>,
+}
+
+#[cw_serde]
+pub struct MilestoneResponse {
+    pub id: u64,
+    pub description: String,
+    pub amount: Uint128,
+    pub deadline: Expiration,
+    pub status: MilestoneStatus,
+    pub yes: Uint128,
+    pub no: Uint128,
+}
+
+#[cw_serde]
+pub struct MilestonesResponse {
+    /// not set if the pool has no milestones
+    pub threshold: Option<Threshold>,
+    pub milestones: Vec<MilestoneResponse>,
+}
+
+#[cw_serde]
+pub struct StreamResponse {
+    pub id: u64,
+    pub denom: String,
+    pub amount: Uint128,
+    pub vested: Uint128,
+    pub claimed: Uint128,
+    pub start: Expiration,
+    pub end: Expiration,
+    pub cliff: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct VestingStatusResponse {
+    pub streams: Vec<StreamResponse>,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum PoolStatus {
+    /// accepting contributions until the target is met or the deadline passes
+    Open,
+    /// target met, the admin can withdraw the funds (or release them milestone by milestone)
+    Succeeded,
+    /// deadline passed without meeting the target, a milestone got rejected or the admin cancelled.
+    /// Contributors can get a refund
+    Failed,
+    /// the admin closed a succeeded pool, nothing can happen anymore
+    Closed,
+}
+
+impl fmt::Display for PoolStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolStatus::Open => write!(f, "open"),
+            PoolStatus::Succeeded => write!(f, "succeeded"),
+            PoolStatus::Failed => write!(f, "failed"),
+            PoolStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum MilestoneStatus {
+    /// contributors can still vote on it
+    Voting,
+    /// approved, the admin can release the tranche
+    Passed,
+    /// cannot pass anymore, contributors can reclaim what was not released yet
+    Rejected,
+    /// the tranche was paid out
+    Released,
+}
+
+impl fmt::Display for MilestoneStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MilestoneStatus::Voting => write!(f, "voting"),
+            MilestoneStatus::Passed => write!(f, "passed"),
+            MilestoneStatus::Rejected => write!(f, "rejected"),
+            MilestoneStatus::Released => write!(f, "released"),
+        }
+    }
+}